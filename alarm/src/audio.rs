@@ -1,15 +1,18 @@
 //! Audio playback.
 
-use std::io::Cursor;
+use std::env;
+use std::fmt::{self, Display, Formatter};
+use std::fs::{self, File};
+use std::io::{BufReader, Cursor};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::time::Duration;
 
-use libpulse_binding::context::{Context, FlagSet as ContextFlagSet, State as PulseState};
-use libpulse_binding::mainloop::standard::{IterateResult, Mainloop};
-use libpulse_binding::volume::{ChannelVolumes, Volume};
 use rodio::{Decoder, OutputStream, OutputStreamBuilder, Sink, Source};
 use tracing::error;
 
 use crate::error::Error;
+use crate::mixer::{self, Mixer};
 
 /// Alarm sound.
 ///
@@ -23,8 +26,20 @@ const ALARM_AUDIO: &[u8] = include_bytes!("../../alarm.flac");
 /// alarm, so we shorten it by 680ms.
 const ALARM_AUDIO_LENGTH: Duration = Duration::from_millis(1500);
 
+/// Alarm metadata key for the alarm's sound file.
+pub const SOUND_METADATA_KEY: &str = "sound";
+
+/// File extensions of supported sound files.
+const SOUND_EXTENSIONS: [&str; 5] = ["flac", "oga", "ogg", "wav", "mp3"];
+
+/// Interval between volume ramp updates.
+const RAMP_INTERVAL: Duration = Duration::from_millis(100);
+
 /// Alarm audio playback.
 pub struct AlarmSound {
+    /// System volume control used for the alarm.
+    mixer: Option<Box<dyn Mixer>>,
+
     _stream: OutputStream,
     sink: Sink,
 }
@@ -35,91 +50,234 @@ impl AlarmSound {
     /// This will start playing the alarm sound immediately and only stop after
     /// the returned [`AlarmSound`] is dropped or [`AlarmSound::stop`] is called
     /// on it.
-    pub fn play() -> Result<Self, Error> {
+    ///
+    /// The embedded alarm sound is played if no `sound` file is specified, or
+    /// if it cannot be decoded.
+    ///
+    /// The playback volume is gradually changed according to the `ramp`.
+    pub fn play(sound: Option<&Path>, ramp: VolumeRamp) -> Result<Self, Error> {
         // Ensure volume is at 100% before playing alarm.
-        if let Err(err) = Pulseaudio::connect().and_then(|mut pa| pa.set_volume(100)) {
-            error!("Pulseaudio error: {err}");
-        }
+        let mixer = mixer::set_volume(100);
 
-        // Parse the audio source file.
+        // Create a sink to allow playback control.
         let stream = OutputStreamBuilder::open_default_stream()?;
-        let audio_buffer = Cursor::new(ALARM_AUDIO);
-        let source = Decoder::new(audio_buffer).unwrap();
+        let sink = Sink::connect_new(stream.mixer());
 
-        // Adjust length and repeat infinitely.
-        let source = source.take_duration(ALARM_AUDIO_LENGTH).repeat_infinite();
+        // Parse the custom audio file.
+        let custom_source = sound.and_then(|path| {
+            Self::decode_file(path)
+                .inspect_err(|err| error!("Could not load alarm sound {path:?}: {err}"))
+                .ok()
+        });
 
-        // Create a sink to allow playback control.
-        let sink = Sink::connect_new(stream.mixer());
-        sink.append(source);
+        match custom_source {
+            Some(source) => sink.append(ramp.apply(source.repeat_infinite())),
+            None => {
+                // Parse the embedded audio source file.
+                let audio_buffer = Cursor::new(ALARM_AUDIO);
+                let source = Decoder::new(audio_buffer).unwrap();
+
+                // Adjust length and repeat infinitely.
+                let source = source.take_duration(ALARM_AUDIO_LENGTH).repeat_infinite();
+                sink.append(ramp.apply(source));
+            },
+        }
 
-        Ok(Self { _stream: stream, sink })
+        Ok(Self { mixer, _stream: stream, sink })
+    }
+
+    /// Decode an audio file.
+    fn decode_file(path: &Path) -> Result<Decoder<BufReader<File>>, Error> {
+        let file = File::open(path)?;
+        Ok(Decoder::new(BufReader::new(file))?)
     }
 
     /// Stop the alarm playback.
-    pub fn stop(self) {
+    ///
+    /// This is equivalent to dropping the [`AlarmSound`].
+    pub fn stop(self) {}
+}
+
+impl Drop for AlarmSound {
+    fn drop(&mut self) {
         self.sink.stop();
+
+        // Restore the system volume from before the alarm.
+        if let Some(mixer) = &mut self.mixer
+            && let Err(err) = mixer.restore()
+        {
+            error!("Failed to restore {} volume: {err}", mixer.name());
+        }
     }
 }
 
-struct Pulseaudio {
-    mainloop: Mainloop,
-    context: Context,
+/// Alarm volume fade-in.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct VolumeRamp {
+    /// Volume percentage at the start of playback.
+    pub start: u8,
+    /// Volume percentage at the end of the ramp.
+    pub target: u8,
+    /// Time until the target volume is reached.
+    pub duration: Duration,
+    /// Volume curve.
+    pub shape: RampShape,
+}
+
+impl Default for VolumeRamp {
+    fn default() -> Self {
+        Self { start: 100, target: 100, duration: Default::default(), shape: Default::default() }
+    }
 }
 
-impl Pulseaudio {
-    /// Connect to the pulseaudio server.
-    fn connect() -> Result<Self, Error> {
-        // Connect with pulseaudio's standard event loop.
-        let crate_name = env!("CARGO_PKG_NAME");
-        let mainloop = Mainloop::new().ok_or(Error::PulseaudioConnection)?;
-        let mut context = Context::new(&mainloop, crate_name).ok_or(Error::PulseaudioConnection)?;
-        context.connect(None, ContextFlagSet::NOFLAGS, None)?;
-
-        let mut pulseaudio = Self { mainloop, context };
-
-        // Wait for connection to be established.
-        loop {
-            pulseaudio.dispatch()?;
-
-            match pulseaudio.context.get_state() {
-                PulseState::Ready => break,
-                PulseState::Failed | PulseState::Terminated => {
-                    return Err(Error::PulseaudioConnection);
-                },
-                _ => (),
-            }
+impl VolumeRamp {
+    /// Get the volume percentage after playing for `elapsed`.
+    ///
+    /// Volumes above 100% are clamped to avoid clipping.
+    pub fn volume_at(&self, elapsed: Duration) -> f32 {
+        let progress = if elapsed >= self.duration {
+            1.
+        } else {
+            elapsed.as_secs_f32() / self.duration.as_secs_f32()
+        };
+
+        let start = self.start.min(100) as f32;
+        let target = self.target.min(100) as f32;
+        match self.shape {
+            RampShape::Linear => start + (target - start) * progress,
+            // Interpolate between decibels, since loudness is perceived logarithmically.
+            RampShape::Logarithmic => {
+                let start = start.max(1.);
+                let target = target.max(1.);
+                start * (target / start).powf(progress)
+            },
         }
+    }
 
-        Ok(pulseaudio)
+    /// Apply the volume ramp to an audio source.
+    fn apply<S>(self, source: S) -> impl Source<Item = S::Item>
+    where
+        S: Source,
+    {
+        let mut elapsed = Duration::ZERO;
+        source.amplify(self.volume_at(elapsed) / 100.).periodic_access(
+            RAMP_INTERVAL,
+            move |source| {
+                source.set_factor(self.volume_at(elapsed) / 100.);
+                elapsed += RAMP_INTERVAL;
+            },
+        )
     }
+}
 
-    /// Set audio volume percentage.
-    fn set_volume(&mut self, volume: u8) -> Result<(), Error> {
-        let volume = Volume(Volume::NORMAL.0 * volume as u32 / 100);
-        let mut volumes = ChannelVolumes::default();
-        volumes.set(ChannelVolumes::CHANNELS_MAX, volume);
+/// Shape of the volume ramp.
+#[derive(Copy, Clone, Default, PartialEq, Eq, Debug)]
+pub enum RampShape {
+    /// Constant volume change over time.
+    #[default]
+    Linear,
+    /// Constant change in perceived loudness over time.
+    Logarithmic,
+}
 
-        let mut introspect = self.context.introspect();
-        introspect.set_sink_volume_by_index(0, &volumes, None);
+impl FromStr for RampShape {
+    type Err = Error;
 
-        self.dispatch()?;
-        self.dispatch()?;
-        self.dispatch()
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "linear" => Ok(Self::Linear),
+            "logarithmic" => Ok(Self::Logarithmic),
+            _ => Err(Error::InvalidRampShape(s.into())),
+        }
     }
+}
 
-    /// Blockingly dispatch the next pulseaudio event.
-    fn dispatch(&mut self) -> Result<(), Error> {
-        match self.mainloop.iterate(true) {
-            IterateResult::Quit(_) => Err(Error::PulseaudioConnection),
-            IterateResult::Err(err) => Err(err.into()),
-            IterateResult::Success(_) => Ok(()),
+impl Display for RampShape {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Linear => write!(f, "linear"),
+            Self::Logarithmic => write!(f, "logarithmic"),
         }
     }
 }
 
-impl Drop for Pulseaudio {
-    fn drop(&mut self) {
-        self.context.disconnect();
+/// Directory containing user-supplied alarm sounds.
+///
+/// This is `$XDG_DATA_HOME/aevum/sounds`, or `~/.local/share/aevum/sounds` if
+/// `$XDG_DATA_HOME` is not set.
+pub fn sounds_dir() -> Option<PathBuf> {
+    let data_home = match env::var_os("XDG_DATA_HOME") {
+        Some(data_home) if !data_home.is_empty() => PathBuf::from(data_home),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".local/share"),
+    };
+    Some(data_home.join("aevum/sounds"))
+}
+
+/// Get the names of all supported files in the [`sounds_dir`].
+///
+/// The sounds are sorted alphabetically.
+pub fn sounds() -> Vec<String> {
+    let entries = match sounds_dir().map(fs::read_dir) {
+        Some(Ok(entries)) => entries,
+        _ => return Vec::new(),
+    };
+
+    let mut sounds: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_file())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| {
+            let extension = Path::new(name).extension().and_then(|extension| extension.to_str());
+            extension.is_some_and(|extension| {
+                SOUND_EXTENSIONS.iter().any(|supported| extension.eq_ignore_ascii_case(supported))
+            })
+        })
+        .collect();
+    sounds.sort_unstable();
+
+    sounds
+}
+
+/// Resolve a sound file path.
+///
+/// Relative paths are resolved from the [`sounds_dir`], while empty paths
+/// refer to the embedded alarm sound.
+pub fn sound_path(sound: &str) -> Option<PathBuf> {
+    if sound.is_empty() {
+        return None;
+    }
+
+    match sounds_dir() {
+        Some(sounds_dir) => Some(sounds_dir.join(sound)),
+        None => Some(PathBuf::from(sound)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn volume_ramp() {
+        let mut ramp = VolumeRamp {
+            start: 10,
+            target: 90,
+            duration: Duration::from_secs(10),
+            shape: RampShape::Linear,
+        };
+
+        assert_eq!(ramp.volume_at(Duration::ZERO), 10.);
+        assert_eq!(ramp.volume_at(Duration::from_secs(5)), 50.);
+        assert_eq!(ramp.volume_at(Duration::from_secs(10)), 90.);
+        assert_eq!(ramp.volume_at(Duration::from_secs(60)), 90.);
+
+        ramp.shape = RampShape::Logarithmic;
+        assert_eq!(ramp.volume_at(Duration::ZERO), 10.);
+        assert_eq!(ramp.volume_at(Duration::from_secs(5)), 30.);
+        assert_eq!(ramp.volume_at(Duration::from_secs(60)), 90.);
+
+        // Ramps without duration start at the target volume.
+        ramp.duration = Duration::ZERO;
+        assert_eq!(ramp.volume_at(Duration::ZERO), 90.);
     }
 }
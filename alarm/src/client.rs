@@ -0,0 +1,170 @@
+//! Shared rezz DBus client.
+
+use std::convert::Infallible;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use rezz::Alarm;
+use tokio::sync::Mutex;
+use tracing::warn;
+use zbus::Connection;
+use zbus::connection::Builder;
+
+use crate::Subscriber;
+use crate::dbus::RezzProxy;
+use crate::error::Error;
+
+/// DBus message bus the rezz daemon is served on.
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub enum BusAddress {
+    #[default]
+    System,
+    Session,
+    /// Custom bus address, like `unix:path=/run/rezz/bus`.
+    Address(String),
+}
+
+impl BusAddress {
+    /// Establish a new connection to this bus.
+    async fn connect(&self) -> Result<Connection, Error> {
+        let builder = match self {
+            Self::System => Builder::system()?,
+            Self::Session => Builder::session()?,
+            Self::Address(address) => Builder::address(address.as_str())?,
+        };
+
+        Ok(builder.build().await?)
+    }
+}
+
+impl FromStr for BusAddress {
+    type Err = Infallible;
+
+    fn from_str(bus: &str) -> Result<Self, Self::Err> {
+        Ok(match bus {
+            "system" => Self::System,
+            "session" => Self::Session,
+            address => Self::Address(address.into()),
+        })
+    }
+}
+
+/// Alarm interface sharing a single DBus connection.
+///
+/// The connection is established on first use and shared by all clones of the
+/// client. If the connection is lost, it is re-established automatically.
+///
+/// Requests are addressed to rezz's well-known bus name, so they reach the
+/// daemon even after it was restarted.
+#[derive(Clone, Default)]
+pub struct Client {
+    inner: Arc<ClientInner>,
+}
+
+impl Client {
+    /// Create a client for the rezz daemon on a specific bus.
+    ///
+    /// This does not connect to the bus until the first request is made.
+    pub fn new(bus: BusAddress) -> Self {
+        Self { inner: Arc::new(ClientInner { bus, connection: Default::default() }) }
+    }
+
+    /// Add a new alarm.
+    pub async fn add(&self, alarm: Alarm) -> Result<(), Error> {
+        self.call(async |rezz| rezz.add_alarm(alarm.clone()).await).await
+    }
+
+    /// Replace an existing alarm with the same ID.
+    pub async fn update(&self, alarm: Alarm) -> Result<(), Error> {
+        self.call(async |rezz| rezz.update_alarm(alarm.clone()).await).await
+    }
+
+    /// Remove an existing alarm.
+    pub async fn remove(&self, id: String) -> Result<(), Error> {
+        self.call(async |rezz| rezz.remove_alarm(id.clone()).await).await
+    }
+
+    /// Enable or disable an alarm without removing it.
+    pub async fn set_enabled(&self, id: String, enabled: bool) -> Result<(), Error> {
+        self.call(async |rezz| rezz.set_alarm_enabled(id.clone(), enabled).await).await
+    }
+
+    /// Finish the current occurrence of an alarm.
+    ///
+    /// Recurring alarms are moved to their next occurrence, while all other
+    /// alarms are removed.
+    pub async fn dismiss(&self, id: String) -> Result<(), Error> {
+        self.call(async |rezz| rezz.dismiss_alarm(id.clone()).await).await
+    }
+
+    /// Delay a ringing alarm by the specified number of minutes.
+    pub async fn snooze(&self, id: String, minutes: u32) -> Result<(), Error> {
+        self.call(async |rezz| rezz.snooze_alarm(id.clone(), minutes).await).await
+    }
+
+    /// Stop ringing a dismissed alarm, so it can no longer be snoozed.
+    pub async fn acknowledge(&self, id: String) -> Result<(), Error> {
+        self.call(async |rezz| rezz.acknowledge_alarm(id.clone()).await).await
+    }
+
+    /// Load the alarm database.
+    pub async fn load(&self) -> Result<Vec<Alarm>, Error> {
+        self.call(async |rezz| rezz.alarms().await).await
+    }
+
+    /// Load all alarms the current user is allowed to modify.
+    pub async fn load_owned(&self) -> Result<Vec<Alarm>, Error> {
+        self.call(async |rezz| rezz.owned_alarms().await).await
+    }
+
+    /// Create a new alarm subscription on the client's connection.
+    pub async fn subscribe(&self) -> Result<Subscriber<'static>, Error> {
+        let connection = self.connection().await?;
+        Subscriber::with_connection(&connection).await
+    }
+
+    /// Get the shared DBus connection, connecting if necessary.
+    async fn connection(&self) -> Result<Connection, Error> {
+        let mut connection = self.inner.connection.lock().await;
+
+        match &*connection {
+            Some(connection) => Ok(connection.clone()),
+            None => {
+                let new_connection = self.inner.bus.connect().await?;
+                *connection = Some(new_connection.clone());
+                Ok(new_connection)
+            },
+        }
+    }
+
+    /// Call a rezz DBus method.
+    ///
+    /// If the connection was lost, the method is retried once on a new
+    /// connection.
+    async fn call<T>(
+        &self,
+        method: impl AsyncFn(RezzProxy<'static>) -> zbus::Result<T>,
+    ) -> Result<T, Error> {
+        let connection = self.connection().await?;
+        let rezz = RezzProxy::new(&connection).await?;
+
+        match method(rezz).await {
+            Err(zbus::Error::InputOutput(err)) => {
+                warn!("Lost DBus connection, reconnecting: {err}");
+                *self.inner.connection.lock().await = None;
+
+                let connection = self.connection().await?;
+                let rezz = RezzProxy::new(&connection).await?;
+                Ok(method(rezz).await?)
+            },
+            result => Ok(result?),
+        }
+    }
+}
+
+/// Client state shared between clones.
+#[derive(Default)]
+struct ClientInner {
+    connection: Mutex<Option<Connection>>,
+    bus: BusAddress,
+}
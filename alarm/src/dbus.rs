@@ -1,4 +1,4 @@
-//! Rezz DBus interface.
+//! DBus interfaces.
 
 use rezz::Alarm;
 use zbus::proxy;
@@ -11,8 +11,35 @@ use zbus::proxy;
 pub trait Rezz {
     async fn add_alarm(&self, alarm: Alarm) -> zbus::Result<()>;
 
+    async fn update_alarm(&self, alarm: Alarm) -> zbus::Result<()>;
+
     async fn remove_alarm(&self, id: String) -> zbus::Result<()>;
 
+    async fn set_alarm_enabled(&self, id: String, enabled: bool) -> zbus::Result<()>;
+
+    async fn dismiss_alarm(&self, id: String) -> zbus::Result<()>;
+
+    async fn snooze_alarm(&self, id: String, minutes: u32) -> zbus::Result<()>;
+
+    async fn acknowledge_alarm(&self, id: String) -> zbus::Result<()>;
+
+    async fn owned_alarms(&self) -> zbus::Result<Vec<Alarm>>;
+
     #[zbus(property)]
     fn alarms(&self) -> zbus::Result<Vec<Alarm>>;
 }
+
+#[proxy(
+    interface = "org.freedesktop.login1.Manager",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1"
+)]
+pub trait LogindManager {
+    async fn inhibit(
+        &self,
+        what: &str,
+        who: &str,
+        why: &str,
+        mode: &str,
+    ) -> zbus::Result<zbus::zvariant::OwnedFd>;
+}
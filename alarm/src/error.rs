@@ -2,7 +2,9 @@
 
 use std::io::Error as IoError;
 
+#[cfg(feature = "pulseaudio")]
 use libpulse_binding::error::PAErr;
+use rezz::error::DBusError;
 
 /// User-facing errors.
 #[derive(thiserror::Error, Debug)]
@@ -15,12 +17,87 @@ pub enum Error {
     AudioPlayback(#[from] rodio::PlayError),
     #[error("audio stream error: {0}")]
     AudioStream(#[from] rodio::StreamError),
+    #[error("audio decoder error: {0}")]
+    AudioDecoder(#[from] rodio::decoder::DecoderError),
+    #[cfg(feature = "pulseaudio")]
     #[error("pulseaudio error: {0}")]
     Pulseaudio(#[from] PAErr),
     #[error("dbus error: {0}")]
-    DBus(#[from] zbus::Error),
+    DBus(zbus::Error),
+    #[error("rezz daemon is not running")]
+    RezzUnavailable,
+    #[error("{0}")]
+    Rezz(DBusError),
+    #[error("alarm has elapsed: {0}")]
+    ElapsedTime(String),
+    #[error("invalid ring duration: {0}")]
+    RingDuration(String),
+    #[error("invalid alarm id: {0}")]
+    IdLength(String),
+    #[error("invalid alarm label: {0}")]
+    LabelLength(String),
+    #[error("invalid alarm metadata: {0}")]
+    MetadataSize(String),
+    #[error("alarm limit reached: {0}")]
+    TooManyAlarms(String),
+    #[error("snooze limit reached: {0}")]
+    SnoozeLimit(String),
     #[error("io error: {0}")]
     Io(#[from] IoError),
+    #[error("{0}")]
+    Nix(#[from] nix::Error),
+    #[cfg(feature = "pulseaudio")]
     #[error("pulseaudio connection error")]
     PulseaudioConnection,
+    #[cfg(feature = "pulseaudio")]
+    #[error("pulseaudio default sink not found")]
+    PulseaudioNoSink,
+    #[cfg(feature = "alsa")]
+    #[error("alsa error: {0}")]
+    Alsa(#[from] alsa::Error),
+    #[cfg(feature = "alsa")]
+    #[error("alsa mixer has no playback volume control")]
+    AlsaNoControl,
+    #[error("no vibration device found")]
+    NoVibrationDevice,
+    #[error("invalid vibration pattern: {0}")]
+    InvalidVibrationPattern(String),
+    #[error("invalid volume ramp shape {0:?}, expected linear or logarithmic")]
+    InvalidRampShape(String),
+}
+
+impl From<zbus::Error> for Error {
+    fn from(err: zbus::Error) -> Self {
+        // Map rezz's typed errors to their variants.
+        match DBusError::from(err) {
+            DBusError::ElapsedTime(msg) => Self::ElapsedTime(msg),
+            DBusError::RingDuration(msg) => Self::RingDuration(msg),
+            DBusError::IdLength(msg) => Self::IdLength(msg),
+            DBusError::LabelLength(msg) => Self::LabelLength(msg),
+            DBusError::MetadataSize(msg) => Self::MetadataSize(msg),
+            DBusError::TooManyAlarms(msg) => Self::TooManyAlarms(msg),
+            DBusError::SnoozeLimit(msg) => Self::SnoozeLimit(msg),
+            DBusError::AlarmExists(id) => Self::AlarmExists(id),
+            DBusError::AlarmNotFound(id) => Self::AlarmNotFound(id),
+            DBusError::ZBus(err) if is_service_missing(&err) => Self::RezzUnavailable,
+            DBusError::ZBus(err) => Self::DBus(err),
+            err => Self::Rezz(err),
+        }
+    }
+}
+
+/// Check if a DBus error was caused by the rezz service being unavailable.
+fn is_service_missing(err: &zbus::Error) -> bool {
+    match err {
+        zbus::Error::MethodError(name, ..) => matches!(
+            name.as_str(),
+            "org.freedesktop.DBus.Error.ServiceUnknown"
+                | "org.freedesktop.DBus.Error.NameHasNoOwner"
+        ),
+        zbus::Error::FDO(err) => matches!(
+            **err,
+            zbus::fdo::Error::ServiceUnknown(_) | zbus::fdo::Error::NameHasNoOwner(_)
+        ),
+        _ => false,
+    }
 }
@@ -1,78 +1,159 @@
 use std::borrow::Cow;
-use std::time::{Duration, SystemTime};
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
 
 use rezz::Alarm;
+use rezz::clock::{Clock, SystemClock};
+use time::{Duration, OffsetDateTime};
+use tokio::time::{self as tokio_time, Instant};
 use tokio_stream::StreamExt;
+use tracing::warn;
 use zbus::Connection;
-use zbus::proxy::PropertyStream;
+use zbus::proxy::{OwnerChangedStream, PropertyChanged, PropertyStream};
 
+use crate::client::Client;
 use crate::dbus::RezzProxy;
 use crate::error::Error;
 
 pub mod audio;
+pub mod client;
 mod dbus;
 pub mod error;
+pub mod logind;
+mod mixer;
 mod timer;
+pub mod vibration;
 
-/// Primary alarm interface.
+/// Initial delay before retrying a failed rezz connection.
+const MIN_RETRY_DELAY: StdDuration = StdDuration::from_secs(1);
+
+/// Maximum delay between rezz connection attempts.
+const MAX_RETRY_DELAY: StdDuration = StdDuration::from_secs(60);
+
+/// Zero-configuration alarm interface for rezz on the system bus.
+///
+/// Every request opens a new DBus connection, use [`Client`] to share a single
+/// connection instead.
 pub struct Alarms;
 
 impl Alarms {
     /// Add a new alarm.
     pub async fn add(&self, alarm: Alarm) -> Result<(), Error> {
-        let connection = Connection::system().await?;
-        let rezz = RezzProxy::new(&connection).await?;
-        rezz.add_alarm(alarm).await?;
-        Ok(())
+        Client::default().add(alarm).await
+    }
+
+    /// Replace an existing alarm with the same ID.
+    pub async fn update(&self, alarm: Alarm) -> Result<(), Error> {
+        Client::default().update(alarm).await
     }
 
     /// Remove an existing alarm.
     pub async fn remove(&self, id: String) -> Result<(), Error> {
-        let connection = Connection::system().await?;
-        let rezz = RezzProxy::new(&connection).await?;
-        rezz.remove_alarm(id).await?;
-        Ok(())
+        Client::default().remove(id).await
+    }
+
+    /// Enable or disable an alarm without removing it.
+    pub async fn set_enabled(&self, id: String, enabled: bool) -> Result<(), Error> {
+        Client::default().set_enabled(id, enabled).await
+    }
+
+    /// Finish the current occurrence of an alarm.
+    ///
+    /// Recurring alarms are moved to their next occurrence, while all other
+    /// alarms are removed.
+    pub async fn dismiss(&self, id: String) -> Result<(), Error> {
+        Client::default().dismiss(id).await
+    }
+
+    /// Delay a ringing alarm by the specified number of minutes.
+    pub async fn snooze(&self, id: String, minutes: u32) -> Result<(), Error> {
+        Client::default().snooze(id, minutes).await
+    }
+
+    /// Stop ringing a dismissed alarm, so it can no longer be snoozed.
+    pub async fn acknowledge(&self, id: String) -> Result<(), Error> {
+        Client::default().acknowledge(id).await
     }
 
     /// Load the alarm database.
     ///
     /// This will create the database, to simplify inotify usage.
     pub async fn load(&self) -> Result<Vec<Alarm>, Error> {
-        let connection = Connection::system().await?;
-        let rezz = RezzProxy::new(&connection).await?;
-        let alarms = rezz.alarms().await?;
-        Ok(alarms)
+        Client::default().load().await
+    }
+
+    /// Load all alarms the current user is allowed to modify.
+    pub async fn load_owned(&self) -> Result<Vec<Alarm>, Error> {
+        Client::default().load_owned().await
     }
 }
 
 /// Subscriber for alarm events.
+///
+/// The subscription can be created before rezz is running and recovers from
+/// rezz restarts, reloading all alarms once rezz is available again.
 pub struct Subscriber<'a> {
-    alarms_stream: PropertyStream<'a, Vec<Alarm>>,
+    connection: Connection,
+    owner_stream: OwnerChangedStream<'a>,
+    alarms_stream: Option<PropertyStream<'a, Vec<Alarm>>>,
     alarms: Vec<Alarm>,
+    clock: Arc<dyn Clock>,
+
+    /// Time of the next reconnection attempt.
+    retry_at: Option<Instant>,
+    /// Delay before the next reconnection attempt after it fails.
+    retry_delay: StdDuration,
+
+    connected: bool,
 }
 
 impl Subscriber<'static> {
     /// Create a new DBus alarm subscription.
     pub async fn new() -> Result<Self, Error> {
-        // Setup DBus connection.
-        let connection = Connection::system().await?;
-        let rezz = RezzProxy::new(&connection).await?;
+        Client::default().subscribe().await
+    }
 
-        // Create listener for alarms change.
-        let mut alarms = rezz.alarms().await?;
-        alarms.sort_unstable();
-        let alarms_stream = rezz.receive_alarms_changed().await;
+    /// Create a new alarm subscription on an existing DBus connection.
+    ///
+    /// This only fails if the connection is unusable, if rezz is not running
+    /// the subscriber will connect to it once it becomes available.
+    pub async fn with_connection(connection: &Connection) -> Result<Self, Error> {
+        // Listen for rezz starting or stopping.
+        let rezz = RezzProxy::new(connection).await?;
+        let owner_stream = rezz.inner().receive_owner_changed().await?;
 
-        Ok(Self { alarms_stream, alarms })
+        let mut subscriber = Self {
+            owner_stream,
+            connection: connection.clone(),
+            clock: Arc::new(SystemClock),
+            retry_delay: MIN_RETRY_DELAY,
+            alarms_stream: Default::default(),
+            connected: Default::default(),
+            retry_at: Default::default(),
+            alarms: Default::default(),
+        };
+
+        if let Err(err) = subscriber.sync().await {
+            warn!("Rezz is not available: {err}");
+        }
+
+        Ok(subscriber)
+    }
+
+    /// Use a different clock for alarm deadlines.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
     }
 
     /// Get the next alarm event.
     pub async fn next(&mut self) -> Option<Event<'_>> {
-        let next_alarm = Self::next_alarm(&mut self.alarms);
+        let next_alarm = Self::next_alarm(&mut self.alarms, self.clock.unix_now());
+        let retry_at = self.retry_at;
 
         tokio::select! {
             // Handle alarm updates.
-            Some(new_alarms) = self.alarms_stream.next() => {
+            Some(new_alarms) = Self::next_change(&mut self.alarms_stream) => {
                 if let Ok(mut alarms) = new_alarms.get().await {
                     // Ensure alarms are always sorted by ring time.
                     alarms.sort_unstable();
@@ -81,8 +162,35 @@ impl Subscriber<'static> {
                     return Some(Event::AlarmsChanged(Cow::Borrowed(&self.alarms)));
                 }
             },
+            // Handle rezz starting or stopping.
+            Some(owner) = self.owner_stream.next() => {
+                let was_connected = self.connected;
+
+                if owner.is_some() {
+                    // Reconnect immediately once rezz is back.
+                    self.retry_delay = MIN_RETRY_DELAY;
+                    if let Err(err) = self.sync().await {
+                        warn!("Could not reconnect to rezz: {err}");
+                    }
+                } else {
+                    // Wait for rezz to start again, instead of polling it.
+                    self.alarms_stream = None;
+                    self.connected = false;
+                    self.retry_at = None;
+                }
+
+                return self.connection_event(was_connected);
+            },
+            // Retry failed reconnection attempts.
+            _ = tokio_time::sleep_until(retry_at.unwrap_or_else(Instant::now)), if retry_at.is_some() => {
+                if let Err(err) = self.sync().await {
+                    warn!("Could not reconnect to rezz: {err}");
+                }
+
+                return self.connection_event(false);
+            },
             // Ring the alarm.
-            _ = Self::wait_alarm(next_alarm) => {
+            _ = Self::wait_alarm(next_alarm, &*self.clock) => {
                 // Remove the alarm once it starts ringing.
                 let next_id = &next_alarm?.id.clone();
                 let index = self.alarms.iter().position(|alarm| &alarm.id == next_id)?;
@@ -99,38 +207,91 @@ impl Subscriber<'static> {
     ///
     /// This list of alarms will always be ordered by alarm time, with the
     /// smallest timestamp being first in the slice.
+    ///
+    /// While rezz is unavailable, this contains the last known alarms.
     pub fn alarms(&self) -> &[Alarm] {
         self.alarms.as_slice()
     }
 
+    /// Check whether rezz is currently available.
+    pub fn connected(&self) -> bool {
+        self.connected
+    }
+
+    /// Reload all alarms and subscribe to their changes.
+    ///
+    /// On failure, another attempt is scheduled with exponential backoff.
+    async fn sync(&mut self) -> Result<(), Error> {
+        let result = async {
+            let rezz = RezzProxy::new(&self.connection).await?;
+            let mut alarms = rezz.alarms().await?;
+            alarms.sort_unstable();
+            let alarms_stream = rezz.receive_alarms_changed().await;
+            Ok::<_, Error>((alarms, alarms_stream))
+        }
+        .await;
+
+        match result {
+            Ok((alarms, alarms_stream)) => {
+                self.alarms_stream = Some(alarms_stream);
+                self.alarms = alarms;
+                self.connected = true;
+                self.retry_delay = MIN_RETRY_DELAY;
+                self.retry_at = None;
+
+                Ok(())
+            },
+            Err(err) => {
+                self.alarms_stream = None;
+                self.connected = false;
+                self.retry_at = Some(Instant::now() + self.retry_delay);
+                self.retry_delay = (self.retry_delay * 2).min(MAX_RETRY_DELAY);
+
+                Err(err)
+            },
+        }
+    }
+
+    /// Get the event for a change in rezz's availability.
+    fn connection_event(&self, was_connected: bool) -> Option<Event<'_>> {
+        match (was_connected, self.connected) {
+            (false, true) => Some(Event::Connected),
+            (true, false) => Some(Event::Disconnected),
+            _ => None,
+        }
+    }
+
+    /// Wait for the next change to the alarms property.
+    async fn next_change(
+        alarms_stream: &mut Option<PropertyStream<'static, Vec<Alarm>>>,
+    ) -> Option<PropertyChanged<'static, Vec<Alarm>>> {
+        alarms_stream.as_mut()?.next().await
+    }
+
     /// Get the next alarm.
     ///
-    /// This will ignore all alarms which are elapsed beyond their ringing
-    /// duration.
+    /// This will ignore all disabled alarms and alarms which are elapsed
+    /// beyond their ringing duration.
     ///
     /// The input slice is sorted to ensure optimal performance.
-    fn next_alarm(alarms: &mut [Alarm]) -> Option<&Alarm> {
-        // Get seconds since unix epoch.
-        let current_secs =
-            SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs();
-
-        // Get the next non-elapsed alarm.
+    fn next_alarm(alarms: &mut [Alarm], now: i64) -> Option<&Alarm> {
+        // Get the next enabled non-elapsed alarm.
         alarms
             .iter()
-            .find(|alarm| alarm.unix_time as u64 + alarm.ring_seconds as u64 >= current_secs)
+            .find(|alarm| alarm.enabled && alarm.ring_time() + alarm.ring_seconds as i64 >= now)
     }
 
     /// Convert alarm to tokio async sleep.
-    async fn wait_alarm(alarm: Option<&Alarm>) -> Result<(), Error> {
+    async fn wait_alarm(alarm: Option<&Alarm>, clock: &dyn Clock) -> Result<(), Error> {
         // Get time until alarm.
         let target = match alarm {
-            Some(alarm) => SystemTime::UNIX_EPOCH + Duration::from_secs(alarm.unix_time as u64),
+            Some(alarm) => OffsetDateTime::UNIX_EPOCH + Duration::seconds(alarm.ring_time()),
             // Default to an hour without alarm present.
-            None => SystemTime::now() + Duration::from_secs(60 * 60),
+            None => clock.now() + Duration::hours(1),
         };
 
         // Wait for timer to elapse.
-        timer::sleep_until(target).await?;
+        timer::sleep_until(target, clock).await?;
 
         Ok(())
     }
@@ -140,4 +301,51 @@ impl Subscriber<'static> {
 pub enum Event<'a> {
     AlarmsChanged(Cow<'a, [Alarm]>),
     Ring(Alarm),
+    /// Rezz became available.
+    ///
+    /// This is followed by an [`Event::AlarmsChanged`] with all alarms.
+    Connected,
+    /// Rezz became unavailable.
+    Disconnected,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn next_alarm_after_suspend() {
+        let mut alarms = vec![Alarm::new("a", 100, 60), Alarm::new("b", 200, 60)];
+
+        // Alarms which are still within their ringing duration are rung.
+        let next = Subscriber::next_alarm(&mut alarms, 150);
+        assert_eq!(next.map(|alarm| alarm.id.as_str()), Some("a"));
+
+        // Alarms which finished ringing during suspend are skipped.
+        let next = Subscriber::next_alarm(&mut alarms, 161);
+        assert_eq!(next.map(|alarm| alarm.id.as_str()), Some("b"));
+
+        let next = Subscriber::next_alarm(&mut alarms, 261);
+        assert_eq!(next, None);
+    }
+
+    #[test]
+    fn next_alarm_snoozed() {
+        let mut snoozed = Alarm::new("a", 100, 60);
+        snoozed.snoozed_until = 300;
+        let mut alarms = vec![Alarm::new("b", 200, 60), snoozed];
+
+        let next = Subscriber::next_alarm(&mut alarms, 270);
+        assert_eq!(next.map(|alarm| alarm.id.as_str()), Some("a"));
+    }
+
+    #[test]
+    fn next_alarm_disabled() {
+        let mut disabled = Alarm::new("a", 100, 60);
+        disabled.enabled = false;
+        let mut alarms = vec![disabled, Alarm::new("b", 200, 60)];
+
+        let next = Subscriber::next_alarm(&mut alarms, 50);
+        assert_eq!(next.map(|alarm| alarm.id.as_str()), Some("b"));
+    }
 }
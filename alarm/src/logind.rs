@@ -0,0 +1,18 @@
+//! Logind session management.
+
+use std::os::fd::OwnedFd;
+
+use zbus::Connection;
+
+use crate::dbus::LogindManagerProxy;
+use crate::error::Error;
+
+/// Prevent the session from going idle.
+///
+/// The inhibitor is active until the returned file descriptor is dropped.
+pub async fn inhibit_idle(who: &str, why: &str) -> Result<OwnedFd, Error> {
+    let connection = Connection::system().await?;
+    let logind = LogindManagerProxy::new(&connection).await?;
+    let fd = logind.inhibit("idle", who, why, "block").await?;
+    Ok(fd.into())
+}
@@ -0,0 +1,104 @@
+//! ALSA mixer volume control.
+
+use alsa::mixer::{Mixer as AlsaMixer, Selem, SelemChannelId, SelemId};
+
+use crate::error::Error;
+use crate::mixer::Mixer;
+
+/// ALSA mixer device.
+const DEVICE: &str = "default";
+
+/// Preferred mixer controls, in order of priority.
+const CONTROLS: [&str; 3] = ["Master", "Speaker", "PCM"];
+
+/// ALSA mixer volume backend.
+#[derive(Default)]
+pub struct Alsa {
+    previous_volume: Option<Vec<ChannelVolume>>,
+}
+
+impl Mixer for Alsa {
+    fn name(&self) -> &'static str {
+        "ALSA"
+    }
+
+    fn set_volume(&mut self, volume: u8) -> Result<(), Error> {
+        let previous_volume = with_control(|selem| {
+            let previous_volume = playback_channels(selem)
+                .map(|channel| {
+                    let volume = selem.get_playback_volume(channel)?;
+                    let switch = match selem.has_playback_switch() {
+                        true => Some(selem.get_playback_switch(channel)?),
+                        false => None,
+                    };
+                    Ok(ChannelVolume { channel, volume, switch })
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+
+            // Scale percentage to the control's volume range.
+            let (min, max) = selem.get_playback_volume_range();
+            selem.set_playback_volume_all(min + (max - min) * volume as i64 / 100)?;
+
+            // Unmute the control.
+            if selem.has_playback_switch() {
+                selem.set_playback_switch_all(1)?;
+            }
+
+            Ok(previous_volume)
+        })?;
+        self.previous_volume = Some(previous_volume);
+
+        Ok(())
+    }
+
+    fn restore(&mut self) -> Result<(), Error> {
+        let previous_volume = match self.previous_volume.take() {
+            Some(previous_volume) => previous_volume,
+            None => return Ok(()),
+        };
+
+        with_control(|selem| {
+            for channel in previous_volume {
+                selem.set_playback_volume(channel.channel, channel.volume)?;
+                if let Some(switch) = channel.switch {
+                    selem.set_playback_switch(channel.channel, switch)?;
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Volume state of a mixer channel.
+struct ChannelVolume {
+    channel: SelemChannelId,
+    volume: i64,
+    switch: Option<i32>,
+}
+
+/// Run a function on the default device's playback volume control.
+fn with_control<T, F>(f: F) -> Result<T, Error>
+where
+    F: FnOnce(&Selem) -> Result<T, Error>,
+{
+    let mixer = AlsaMixer::new(DEVICE, false)?;
+
+    // Use the first preferred control, falling back to any playback control.
+    let selem = CONTROLS
+        .iter()
+        .filter_map(|name| mixer.find_selem(&SelemId::new(name, 0)))
+        .chain(mixer.iter().filter_map(Selem::new))
+        .find(|selem| selem.has_playback_volume())
+        .ok_or(Error::AlsaNoControl)?;
+
+    f(&selem)
+}
+
+/// Get all playback channels of a mixer control.
+fn playback_channels<'a>(selem: &'a Selem) -> impl Iterator<Item = SelemChannelId> + 'a {
+    SelemChannelId::all()
+        .iter()
+        .copied()
+        .filter(|channel| !matches!(channel, SelemChannelId::Unknown | SelemChannelId::Last))
+        .filter(|channel| selem.has_playback_channel(*channel))
+}
@@ -0,0 +1,55 @@
+//! System volume control.
+
+use tracing::{debug, warn};
+
+use crate::error::Error;
+#[cfg(feature = "alsa")]
+use crate::mixer::alsa::Alsa;
+#[cfg(feature = "pulseaudio")]
+use crate::mixer::pulseaudio::Pulseaudio;
+
+#[cfg(feature = "alsa")]
+mod alsa;
+#[cfg(feature = "pulseaudio")]
+mod pulseaudio;
+
+/// System volume backend.
+pub trait Mixer {
+    /// Backend name used for logging.
+    fn name(&self) -> &'static str;
+
+    /// Unmute the default output and set its volume percentage.
+    ///
+    /// The previous state of the output is saved, to allow restoring it with
+    /// [`Mixer::restore`].
+    fn set_volume(&mut self, volume: u8) -> Result<(), Error>;
+
+    /// Restore the output state from before [`Mixer::set_volume`] was called.
+    fn restore(&mut self) -> Result<(), Error>;
+}
+
+/// Set the system volume using the first available backend.
+///
+/// Backends are tried in the order PulseAudio and ALSA, skipping backends
+/// which were disabled at compile time.
+///
+/// The returned backend should be used to restore the previous volume.
+pub fn set_volume(volume: u8) -> Option<Box<dyn Mixer>> {
+    let backends: Vec<Box<dyn Mixer>> = vec![
+        #[cfg(feature = "pulseaudio")]
+        Box::new(Pulseaudio::default()),
+        #[cfg(feature = "alsa")]
+        Box::new(Alsa::default()),
+    ];
+
+    for mut backend in backends {
+        match backend.set_volume(volume) {
+            Ok(()) => return Some(backend),
+            Err(err) => debug!("{} volume control unavailable: {err}", backend.name()),
+        }
+    }
+
+    warn!("No system volume control available, playing at current volume");
+
+    None
+}
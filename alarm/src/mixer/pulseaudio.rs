@@ -0,0 +1,167 @@
+//! PulseAudio volume control.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use libpulse_binding::callbacks::ListResult;
+use libpulse_binding::context::{Context, FlagSet as ContextFlagSet, State as PulseState};
+use libpulse_binding::mainloop::standard::{IterateResult, Mainloop};
+use libpulse_binding::operation::{Operation, State as OperationState};
+use libpulse_binding::volume::{ChannelVolumes, Volume};
+
+use crate::error::Error;
+use crate::mixer::Mixer;
+
+/// PulseAudio volume backend.
+///
+/// This also supports PipeWire through its PulseAudio compatibility layer.
+#[derive(Default)]
+pub struct Pulseaudio {
+    previous_volume: Option<SinkVolume>,
+}
+
+impl Mixer for Pulseaudio {
+    fn name(&self) -> &'static str {
+        "PulseAudio"
+    }
+
+    fn set_volume(&mut self, volume: u8) -> Result<(), Error> {
+        let mut connection = Connection::new()?;
+
+        let previous_volume = connection.default_sink_volume()?;
+        connection.set_volume(&previous_volume.sink, volume)?;
+        self.previous_volume = Some(previous_volume);
+
+        Ok(())
+    }
+
+    fn restore(&mut self) -> Result<(), Error> {
+        match self.previous_volume.take() {
+            Some(previous_volume) => Connection::new()?.restore(previous_volume),
+            None => Ok(()),
+        }
+    }
+}
+
+/// PulseAudio server connection.
+struct Connection {
+    mainloop: Mainloop,
+    context: Context,
+}
+
+impl Connection {
+    /// Connect to the pulseaudio server.
+    fn new() -> Result<Self, Error> {
+        // Connect with pulseaudio's standard event loop.
+        let crate_name = env!("CARGO_PKG_NAME");
+        let mainloop = Mainloop::new().ok_or(Error::PulseaudioConnection)?;
+        let mut context = Context::new(&mainloop, crate_name).ok_or(Error::PulseaudioConnection)?;
+        context.connect(None, ContextFlagSet::NOFLAGS, None)?;
+
+        let mut connection = Self { mainloop, context };
+
+        // Wait for connection to be established.
+        loop {
+            connection.dispatch()?;
+
+            match connection.context.get_state() {
+                PulseState::Ready => break,
+                PulseState::Failed | PulseState::Terminated => {
+                    return Err(Error::PulseaudioConnection);
+                },
+                _ => (),
+            }
+        }
+
+        Ok(connection)
+    }
+
+    /// Get the current volume of the default sink.
+    fn default_sink_volume(&mut self) -> Result<SinkVolume, Error> {
+        let introspect = self.context.introspect();
+
+        // Get the name of the default sink.
+        let sink = Rc::new(RefCell::new(None));
+        let sink_callback = sink.clone();
+        let operation = introspect.get_server_info(move |info| {
+            *sink_callback.borrow_mut() = info.default_sink_name.as_ref().map(|s| s.to_string());
+        });
+        self.wait(&operation)?;
+        let sink = sink.take().ok_or(Error::PulseaudioNoSink)?;
+
+        // Get the sink's current volume.
+        let volume = Rc::new(RefCell::new(None));
+        let volume_callback = volume.clone();
+        let operation = introspect.get_sink_info_by_name(&sink, move |result| {
+            if let ListResult::Item(info) = result {
+                *volume_callback.borrow_mut() = Some((info.volume, info.mute));
+            }
+        });
+        self.wait(&operation)?;
+        let (volume, mute) = volume.take().ok_or(Error::PulseaudioNoSink)?;
+
+        Ok(SinkVolume { sink, volume, mute })
+    }
+
+    /// Unmute a sink and set its volume percentage.
+    fn set_volume(&mut self, sink: &str, volume: u8) -> Result<(), Error> {
+        let volume = Volume(Volume::NORMAL.0 * volume as u32 / 100);
+        let mut volumes = ChannelVolumes::default();
+        volumes.set(ChannelVolumes::CHANNELS_MAX, volume);
+
+        self.set_sink_volume(sink, &volumes, false)
+    }
+
+    /// Restore a sink's previous volume.
+    fn restore(&mut self, volume: SinkVolume) -> Result<(), Error> {
+        self.set_sink_volume(&volume.sink, &volume.volume, volume.mute)
+    }
+
+    /// Update a sink's volume and mute state.
+    fn set_sink_volume(
+        &mut self,
+        sink: &str,
+        volumes: &ChannelVolumes,
+        mute: bool,
+    ) -> Result<(), Error> {
+        let mut introspect = self.context.introspect();
+        let volume_operation = introspect.set_sink_volume_by_name(sink, volumes, None);
+        let mute_operation = introspect.set_sink_mute_by_name(sink, mute, None);
+
+        self.wait(&volume_operation)?;
+        self.wait(&mute_operation)
+    }
+
+    /// Dispatch events until an operation is completed.
+    fn wait<T: ?Sized>(&mut self, operation: &Operation<T>) -> Result<(), Error> {
+        loop {
+            match operation.get_state() {
+                OperationState::Running => self.dispatch()?,
+                OperationState::Done => return Ok(()),
+                OperationState::Cancelled => return Err(Error::PulseaudioConnection),
+            }
+        }
+    }
+
+    /// Blockingly dispatch the next pulseaudio event.
+    fn dispatch(&mut self) -> Result<(), Error> {
+        match self.mainloop.iterate(true) {
+            IterateResult::Quit(_) => Err(Error::PulseaudioConnection),
+            IterateResult::Err(err) => Err(err.into()),
+            IterateResult::Success(_) => Ok(()),
+        }
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.context.disconnect();
+    }
+}
+
+/// Volume state of a pulseaudio sink.
+struct SinkVolume {
+    sink: String,
+    volume: ChannelVolumes,
+    mute: bool,
+}
@@ -9,8 +9,9 @@
 use std::io::Error as IoError;
 use std::mem::MaybeUninit;
 use std::ptr;
-use std::time::SystemTime;
 
+use rezz::clock::Clock;
+use time::OffsetDateTime;
 use tokio::signal::unix::{SignalKind, signal};
 
 /// Create a new timer.
@@ -70,6 +71,9 @@ unsafe fn remove_timer(timer: libc::timer_t) -> Result<(), IoError> {
 /// system is suspended at the time that the timer would expire, the timer
 /// expires immediately after the system resumes from sleep.
 ///
+/// The target is compared against `clock` every time the timer expires, so
+/// this only returns once `clock` has reached the target.
+///
 /// # Errors
 ///
 /// Returns an error if:
@@ -80,19 +84,18 @@ unsafe fn remove_timer(timer: libc::timer_t) -> Result<(), IoError> {
 ///  - Setting the timer (via `timer_settime(2)`) fails.
 ///  - Cleaning up the timer after it has triggered (via `timer_delete(2)`)
 ///    fails.
-pub async fn sleep_until(target: SystemTime) -> Result<(), IoError> {
+pub async fn sleep_until(target: OffsetDateTime, clock: &dyn Clock) -> Result<(), IoError> {
     // We must schedule our signal handler before the first signal appears.
     let mut alarm = signal(SignalKind::alarm())?;
 
     loop {
-        let now = SystemTime::now();
-        let remaining = match target.duration_since(now) {
-            Ok(remaining) => remaining,
-            Err(_) => return Ok(()),
-        };
+        let remaining = target - clock.now();
+        if remaining.is_negative() {
+            return Ok(());
+        }
 
         // Set a timer for the specified time.
-        let timer = unsafe { add_timer(remaining.as_secs() as i64)? };
+        let timer = unsafe { add_timer(remaining.whole_seconds())? };
 
         // Wait for the signal.
         alarm.recv().await;
@@ -100,3 +103,40 @@ pub async fn sleep_until(target: SystemTime) -> Result<(), IoError> {
         unsafe { remove_timer(timer)? }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration as StdDuration;
+
+    use rezz::clock::FakeClock;
+    use time::Duration;
+    use time::macros::datetime;
+    use tokio::time as tokio_time;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn elapsed_target() {
+        let clock = FakeClock::new(datetime!(2025-10-17 12:00 UTC));
+        let target = datetime!(2025-10-17 11:00 UTC);
+
+        let sleep = tokio_time::timeout(StdDuration::from_secs(1), sleep_until(target, &clock));
+        assert!(sleep.await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn elapse_during_suspend() {
+        let clock = FakeClock::new(datetime!(2025-10-17 12:00 UTC));
+        let target = datetime!(2025-10-17 12:00:01 UTC);
+
+        // Jump far beyond the target while the timer is active.
+        let sleep = tokio_time::timeout(StdDuration::from_secs(5), sleep_until(target, &clock));
+        let suspend = async {
+            tokio::task::yield_now().await;
+            clock.advance(Duration::hours(8));
+        };
+
+        let (sleep, _) = tokio::join!(sleep, suspend);
+        assert!(sleep.unwrap().is_ok());
+    }
+}
@@ -0,0 +1,255 @@
+//! Vibration through the Linux force feedback API.
+
+use std::fmt::{self, Display, Formatter};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::os::fd::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use std::{mem, slice};
+
+use libc::{ff_effect, ff_periodic_effect, ff_rumble_effect, input_event};
+use tracing::error;
+
+use crate::error::Error;
+
+/// Force feedback event type.
+const EV_FF: u16 = 0x15;
+
+/// Rumble force feedback effect.
+const FF_RUMBLE: u16 = 0x50;
+/// Periodic force feedback effect.
+const FF_PERIODIC: u16 = 0x51;
+/// Sine wave form for periodic effects.
+const FF_SINE: u16 = 0x5A;
+
+/// Directory containing evdev device nodes.
+const INPUT_DIR: &str = "/dev/input";
+
+nix::ioctl_read_buf!(eviocgbit_ff, b'E', 0x20 + EV_FF, u8);
+nix::ioctl_write_ptr!(eviocsff, b'E', 0x80, ff_effect);
+nix::ioctl_write_int!(eviocrmff, b'E', 0x81);
+
+/// Active vibration.
+///
+/// The vibration pattern is repeated until this is dropped or
+/// [`Vibration::stop`] is called on it.
+pub struct Vibration {
+    stop_tx: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Vibration {
+    /// Start vibrating.
+    ///
+    /// If no `device` is specified, the first evdev device with rumble or
+    /// periodic force feedback support is used.
+    pub fn start(pattern: VibrationPattern, device: Option<&Path>) -> Result<Self, Error> {
+        let vibrator = match device {
+            Some(path) => Vibrator::open(path)?,
+            None => Vibrator::find()?,
+        };
+
+        let (stop_tx, stop_rx) = mpsc::channel();
+        let thread = thread::spawn(move || {
+            let mut vibrator = vibrator;
+
+            loop {
+                for (i, duration) in pattern.0.iter().enumerate() {
+                    // Alternate between vibration and pause.
+                    let result = if i % 2 == 0 { vibrator.play() } else { vibrator.pause() };
+                    if let Err(err) = result {
+                        error!("Vibration failed: {err}");
+                        return;
+                    }
+
+                    match stop_rx.recv_timeout(*duration) {
+                        Err(RecvTimeoutError::Timeout) => (),
+                        _ => return,
+                    }
+                }
+            }
+        });
+
+        Ok(Self { stop_tx: Some(stop_tx), thread: Some(thread) })
+    }
+
+    /// Stop the vibration.
+    ///
+    /// This is equivalent to dropping the [`Vibration`].
+    pub fn stop(self) {}
+}
+
+impl Drop for Vibration {
+    fn drop(&mut self) {
+        // Notify the vibration thread and wait for it to clean up the effect.
+        self.stop_tx = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Vibration pattern.
+///
+/// Durations alternate between vibration and pause, starting with vibration.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct VibrationPattern(Vec<Duration>);
+
+impl VibrationPattern {
+    pub fn new(durations: Vec<Duration>) -> Result<Self, Error> {
+        if durations.iter().step_by(2).all(|duration| duration.is_zero()) {
+            return Err(Error::InvalidVibrationPattern(
+                "pattern must contain a vibration longer than 0ms".into(),
+            ));
+        }
+
+        Ok(Self(durations))
+    }
+}
+
+impl FromStr for VibrationPattern {
+    type Err = Error;
+
+    /// Parse comma-separated milliseconds, like `500,250,500,1000`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let durations = s
+            .split(',')
+            .map(|millis| match u64::from_str(millis.trim()) {
+                Ok(millis) => Ok(Duration::from_millis(millis)),
+                Err(_) => {
+                    Err(Error::InvalidVibrationPattern(format!("invalid duration {millis:?}")))
+                },
+            })
+            .collect::<Result<_, _>>()?;
+        Self::new(durations)
+    }
+}
+
+impl Display for VibrationPattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (i, duration) in self.0.iter().enumerate() {
+            if i != 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{}", duration.as_millis())?;
+        }
+        Ok(())
+    }
+}
+
+/// Force feedback device with an uploaded vibration effect.
+struct Vibrator {
+    file: File,
+    effect_id: i16,
+    playing: bool,
+}
+
+impl Vibrator {
+    /// Find the first device supporting vibration.
+    fn find() -> Result<Self, Error> {
+        let mut devices: Vec<PathBuf> = fs::read_dir(INPUT_DIR)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.file_name().is_some_and(|n| n.to_string_lossy().starts_with("event"))
+            })
+            .collect();
+        devices.sort_unstable();
+
+        devices.iter().find_map(|path| Self::open(path).ok()).ok_or(Error::NoVibrationDevice)
+    }
+
+    /// Open a force feedback device and upload the vibration effect.
+    fn open(path: &Path) -> Result<Self, Error> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+
+        // Check for supported force feedback effects.
+        let mut features = [0u8; 16];
+        unsafe { eviocgbit_ff(file.as_raw_fd(), &mut features)? };
+        let supports = |effect: u16| features[effect as usize / 8] & (1 << (effect % 8)) != 0;
+
+        let mut effect: ff_effect = unsafe { mem::zeroed() };
+        effect.id = -1;
+        if supports(FF_RUMBLE) {
+            effect.type_ = FF_RUMBLE;
+            let rumble = ff_rumble_effect { strong_magnitude: u16::MAX, weak_magnitude: u16::MAX };
+            unsafe { effect.u.as_mut_ptr().cast::<ff_rumble_effect>().write_unaligned(rumble) };
+        } else if supports(FF_PERIODIC) {
+            effect.type_ = FF_PERIODIC;
+            let mut periodic: ff_periodic_effect = unsafe { mem::zeroed() };
+            periodic.waveform = FF_SINE;
+            periodic.period = 10;
+            periodic.magnitude = i16::MAX;
+            unsafe { effect.u.as_mut_ptr().cast::<ff_periodic_effect>().write_unaligned(periodic) };
+        } else {
+            return Err(Error::NoVibrationDevice);
+        }
+
+        // Upload the effect, which assigns its ID.
+        unsafe { eviocsff(file.as_raw_fd(), &mut effect as *mut _ as *const _)? };
+
+        Ok(Self { file, effect_id: effect.id, playing: false })
+    }
+
+    /// Start the vibration effect.
+    fn play(&mut self) -> Result<(), Error> {
+        self.write_event(1)?;
+        self.playing = true;
+        Ok(())
+    }
+
+    /// Stop the vibration effect.
+    fn pause(&mut self) -> Result<(), Error> {
+        self.write_event(0)?;
+        self.playing = false;
+        Ok(())
+    }
+
+    /// Write a force feedback event for the vibration effect.
+    fn write_event(&mut self, value: i32) -> Result<(), Error> {
+        let mut event: input_event = unsafe { mem::zeroed() };
+        event.type_ = EV_FF;
+        event.code = self.effect_id as u16;
+        event.value = value;
+
+        let size = mem::size_of::<input_event>();
+        let bytes = unsafe { slice::from_raw_parts(&event as *const _ as *const u8, size) };
+        self.file.write_all(bytes)?;
+
+        Ok(())
+    }
+}
+
+impl Drop for Vibrator {
+    fn drop(&mut self) {
+        if self.playing
+            && let Err(err) = self.pause()
+        {
+            error!("Failed to stop vibration: {err}");
+        }
+
+        if let Err(err) = unsafe { eviocrmff(self.file.as_raw_fd(), self.effect_id as _) } {
+            error!("Failed to remove vibration effect: {err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_pattern() {
+        let pattern = VibrationPattern::from_str("500, 250,0").unwrap();
+        assert_eq!(pattern.0, [500, 250, 0].map(Duration::from_millis));
+        assert_eq!(pattern.to_string(), "500,250,0");
+
+        assert!(VibrationPattern::from_str("").is_err());
+        assert!(VibrationPattern::from_str("0,500").is_err());
+        assert!(VibrationPattern::from_str("500,x").is_err());
+    }
+}
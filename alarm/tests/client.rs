@@ -0,0 +1,48 @@
+//! Shared client integration tests.
+
+use std::sync::Arc;
+
+use alarm::client::{BusAddress, Client};
+use alarm::error::Error;
+use rezz::clock::SystemClock;
+use rezz::testing::{TestBus, owned_alarm};
+use time::OffsetDateTime;
+
+#[tokio::test]
+async fn typed_errors() {
+    let Some(bus) = TestBus::new("typed-errors") else { return };
+    let client = Client::new(BusAddress::Address(bus.address.clone()));
+
+    let err = client.load().await.unwrap_err();
+    assert!(matches!(err, Error::RezzUnavailable), "unexpected error: {err}");
+
+    bus.serve(Arc::new(SystemClock)).await;
+
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    let alarm = owned_alarm("a", now + 3600, 60);
+    client.add(alarm.clone()).await.unwrap();
+    assert_eq!(client.load().await.unwrap(), vec![alarm.clone()]);
+
+    let err = client.add(alarm).await.unwrap_err();
+    assert!(matches!(&err, Error::AlarmExists(id) if id == "a"), "unexpected error: {err}");
+
+    let err = client.remove("b".into()).await.unwrap_err();
+    assert!(matches!(&err, Error::AlarmNotFound(id) if id == "b"), "unexpected error: {err}");
+}
+
+#[tokio::test]
+async fn reconnect() {
+    let Some(mut bus) = TestBus::new("reconnect") else { return };
+    bus.serve(Arc::new(SystemClock)).await;
+
+    let client = Client::new(BusAddress::Address(bus.address.clone()));
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    let alarm = owned_alarm("a", now + 3600, 60);
+    client.add(alarm.clone()).await.unwrap();
+
+    // Restart the bus, dropping all existing connections.
+    bus.restart();
+    bus.serve(Arc::new(SystemClock)).await;
+
+    assert_eq!(client.load().await.unwrap(), vec![alarm]);
+}
@@ -0,0 +1,106 @@
+//! Alarm subscription integration tests.
+
+use std::slice;
+use std::sync::Arc;
+use std::time::Duration;
+
+use alarm::{Event, Subscriber};
+use rezz::clock::SystemClock;
+use rezz::testing::{TestBus, owned_alarm};
+use rezz::{Alarm, server};
+use time::OffsetDateTime;
+use tokio::time as tokio_time;
+use zbus::Proxy;
+
+/// Wait for the next subscriber event.
+async fn next_event(subscriber: &mut Subscriber<'static>) -> OwnedEvent {
+    let event = tokio_time::timeout(Duration::from_secs(5), subscriber.next()).await;
+    match event.expect("subscriber event timeout") {
+        Some(Event::AlarmsChanged(alarms)) => OwnedEvent::AlarmsChanged(alarms.into_owned()),
+        Some(Event::Ring(alarm)) => OwnedEvent::Ring(alarm),
+        Some(Event::Connected) => OwnedEvent::Connected,
+        Some(Event::Disconnected) => OwnedEvent::Disconnected,
+        None => OwnedEvent::None,
+    }
+}
+
+/// Subscriber event without borrowed data.
+#[derive(PartialEq, Debug)]
+enum OwnedEvent {
+    AlarmsChanged(Vec<Alarm>),
+    Ring(Alarm),
+    Connected,
+    Disconnected,
+    None,
+}
+
+#[tokio::test]
+async fn alarm_events() {
+    let Some(bus) = TestBus::new("alarm-events") else { return };
+    bus.serve(Arc::new(SystemClock)).await;
+
+    let connection = bus.connect().await;
+    let mut subscriber = Subscriber::with_connection(&connection).await.unwrap();
+    assert_eq!(subscriber.alarms(), &[]);
+
+    // Skip initial property value.
+    assert_eq!(next_event(&mut subscriber).await, OwnedEvent::AlarmsChanged(Vec::new()));
+
+    let rezz = Proxy::new(&connection, server::NAME, server::PATH, "org.catacombing.rezz");
+    let rezz = rezz.await.unwrap();
+
+    // Add an alarm which rings immediately.
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    let alarm = owned_alarm("a", now + 1, 60);
+    let _: () = rezz.call("AddAlarm", &(alarm.clone(),)).await.unwrap();
+    let expected = OwnedEvent::AlarmsChanged(vec![alarm.clone()]);
+    assert_eq!(next_event(&mut subscriber).await, expected);
+
+    assert_eq!(next_event(&mut subscriber).await, OwnedEvent::Ring(alarm.clone()));
+    assert_eq!(subscriber.alarms(), &[]);
+
+    // Dismiss the alarm, like clients would once it starts ringing.
+    let _: () = rezz.call("DismissAlarm", &(alarm.id,)).await.unwrap();
+    assert_eq!(next_event(&mut subscriber).await, OwnedEvent::AlarmsChanged(Vec::new()));
+
+    // Removals are reported as alarm changes.
+    let later = owned_alarm("b", now + 3600, 60);
+    let _: () = rezz.call("AddAlarm", &(later.clone(),)).await.unwrap();
+    assert_eq!(next_event(&mut subscriber).await, OwnedEvent::AlarmsChanged(vec![later.clone()]));
+
+    let _: () = rezz.call("RemoveAlarm", &(later.id,)).await.unwrap();
+    assert_eq!(next_event(&mut subscriber).await, OwnedEvent::AlarmsChanged(Vec::new()));
+}
+
+#[tokio::test]
+async fn rezz_restart() {
+    let Some(bus) = TestBus::new("rezz-restart") else { return };
+
+    // Subscribe before rezz is running.
+    let connection = bus.connect().await;
+    let mut subscriber = Subscriber::with_connection(&connection).await.unwrap();
+    assert!(!subscriber.connected());
+
+    let (server_connection, _) = bus.serve_connection(Arc::new(SystemClock)).await;
+    assert_eq!(next_event(&mut subscriber).await, OwnedEvent::Connected);
+    assert_eq!(next_event(&mut subscriber).await, OwnedEvent::AlarmsChanged(Vec::new()));
+
+    let rezz = Proxy::new(&connection, server::NAME, server::PATH, "org.catacombing.rezz");
+    let rezz = rezz.await.unwrap();
+
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    let alarm = owned_alarm("a", now + 3600, 60);
+    let _: () = rezz.call("AddAlarm", &(alarm.clone(),)).await.unwrap();
+    assert_eq!(next_event(&mut subscriber).await, OwnedEvent::AlarmsChanged(vec![alarm.clone()]));
+
+    // Alarms are kept while rezz is unavailable.
+    server_connection.release_name(server::NAME).await.unwrap();
+    assert_eq!(next_event(&mut subscriber).await, OwnedEvent::Disconnected);
+    assert!(!subscriber.connected());
+    assert_eq!(subscriber.alarms(), slice::from_ref(&alarm));
+
+    // Alarms are reloaded once rezz is back.
+    bus.serve(Arc::new(SystemClock)).await;
+    assert_eq!(next_event(&mut subscriber).await, OwnedEvent::Connected);
+    assert_eq!(next_event(&mut subscriber).await, OwnedEvent::AlarmsChanged(vec![alarm]));
+}
@@ -0,0 +1,154 @@
+use std::ffi::CStr;
+use std::fs::{self, File, OpenOptions};
+use std::io::Read;
+use std::os::fd::AsRawFd;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+use std::{mem, slice, thread};
+
+use alarm::vibration::{Vibration, VibrationPattern};
+use libc::{input_event, uinput_ff_erase, uinput_ff_upload, uinput_setup};
+
+const EV_FF: u16 = 0x15;
+const EV_UINPUT: u16 = 0x0101;
+const FF_RUMBLE: u16 = 0x50;
+const UI_FF_UPLOAD: u16 = 1;
+const UI_FF_ERASE: u16 = 2;
+
+nix::ioctl_none!(ui_dev_create, b'U', 1);
+nix::ioctl_write_ptr!(ui_dev_setup, b'U', 3, uinput_setup);
+nix::ioctl_read_buf!(ui_get_sysname, b'U', 44, u8);
+nix::ioctl_write_int!(ui_set_evbit, b'U', 100);
+nix::ioctl_write_int!(ui_set_ffbit, b'U', 107);
+nix::ioctl_readwrite!(ui_begin_ff_upload, b'U', 200, uinput_ff_upload);
+nix::ioctl_write_ptr!(ui_end_ff_upload, b'U', 201, uinput_ff_upload);
+nix::ioctl_readwrite!(ui_begin_ff_erase, b'U', 202, uinput_ff_erase);
+nix::ioctl_write_ptr!(ui_end_ff_erase, b'U', 203, uinput_ff_erase);
+
+#[test]
+fn vibration_pattern() {
+    let Some((device, events)) = VirtualVibrator::spawn() else {
+        eprintln!("Skipping test, could not create uinput device");
+        return;
+    };
+
+    let pattern = VibrationPattern::from_str("500,500").unwrap();
+    let vibration = Vibration::start(pattern, Some(&device)).unwrap();
+
+    // Effect is uploaded and played, then paused.
+    let timeout = Duration::from_secs(5);
+    assert_eq!(events.recv_timeout(timeout), Ok(Effect::Upload));
+    assert_eq!(events.recv_timeout(timeout), Ok(Effect::Play(1)));
+    assert_eq!(events.recv_timeout(timeout), Ok(Effect::Play(0)));
+    assert_eq!(events.recv_timeout(timeout), Ok(Effect::Play(1)));
+
+    // Stopping halts playback and removes the effect.
+    vibration.stop();
+    loop {
+        match events.recv_timeout(timeout) {
+            Ok(Effect::Play(0)) => continue,
+            effect => {
+                assert_eq!(effect, Ok(Effect::Erase));
+                break;
+            },
+        }
+    }
+}
+
+/// Force feedback requests received by the virtual device.
+#[derive(PartialEq, Eq, Debug)]
+enum Effect {
+    Upload,
+    Play(i32),
+    Erase,
+}
+
+/// Virtual uinput device with rumble support.
+struct VirtualVibrator;
+
+impl VirtualVibrator {
+    /// Create the device and forward its force feedback requests.
+    ///
+    /// Returns the path of the device's evdev node.
+    fn spawn() -> Option<(PathBuf, Receiver<Effect>)> {
+        let uinput = OpenOptions::new().read(true).write(true).open("/dev/uinput").ok()?;
+        let fd = uinput.as_raw_fd();
+
+        let mut setup: uinput_setup = unsafe { mem::zeroed() };
+        for (dst, src) in setup.name.iter_mut().zip(b"aevum test vibrator") {
+            *dst = *src as _;
+        }
+        setup.ff_effects_max = 1;
+
+        let mut sysname = [0u8; 64];
+        unsafe {
+            ui_set_evbit(fd, EV_FF as _).ok()?;
+            ui_set_ffbit(fd, FF_RUMBLE as _).ok()?;
+            ui_dev_setup(fd, &setup).ok()?;
+            ui_dev_create(fd).ok()?;
+            ui_get_sysname(fd, &mut sysname).ok()?;
+        }
+
+        // Find the evdev node for the new device.
+        let sysname = CStr::from_bytes_until_nul(&sysname).ok()?.to_str().ok()?;
+        let sys_dir = PathBuf::from("/sys/devices/virtual/input").join(sysname);
+        let device = (0..50).find_map(|_| {
+            let event = fs::read_dir(&sys_dir)
+                .ok()?
+                .filter_map(|entry| entry.ok())
+                .find(|entry| entry.file_name().to_string_lossy().starts_with("event"));
+            let device = event.map(|event| PathBuf::from("/dev/input").join(event.file_name()));
+            if device.as_ref().is_none_or(|device| !device.exists()) {
+                thread::sleep(Duration::from_millis(100));
+                return None;
+            }
+            device
+        })?;
+
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || Self::handle_events(uinput, |effect| tx.send(effect).is_ok()));
+
+        Some((device, rx))
+    }
+
+    /// Respond to force feedback requests.
+    fn handle_events(mut uinput: File, mut callback: impl FnMut(Effect) -> bool) {
+        let fd = uinput.as_raw_fd();
+        let mut event: input_event = unsafe { mem::zeroed() };
+        let size = mem::size_of::<input_event>();
+
+        loop {
+            let bytes = unsafe { slice::from_raw_parts_mut(&mut event as *mut _ as *mut u8, size) };
+            if uinput.read_exact(bytes).is_err() {
+                return;
+            }
+
+            let effect = match (event.type_, event.code) {
+                (EV_UINPUT, UI_FF_UPLOAD) => unsafe {
+                    let mut upload: uinput_ff_upload = mem::zeroed();
+                    upload.request_id = event.value as u32;
+                    ui_begin_ff_upload(fd, &mut upload).unwrap();
+                    upload.retval = 0;
+                    ui_end_ff_upload(fd, &upload).unwrap();
+                    Effect::Upload
+                },
+                (EV_UINPUT, UI_FF_ERASE) => unsafe {
+                    let mut erase: uinput_ff_erase = mem::zeroed();
+                    erase.request_id = event.value as u32;
+                    ui_begin_ff_erase(fd, &mut erase).unwrap();
+                    erase.retval = 0;
+                    ui_end_ff_erase(fd, &erase).unwrap();
+                    Effect::Erase
+                },
+                (EV_FF, _) => Effect::Play(event.value),
+                _ => continue,
+            };
+
+            if !callback(effect) {
+                return;
+            }
+        }
+    }
+}
@@ -1,25 +1,43 @@
 //! Alarm clock CLI interface.
 
 use std::num::ParseIntError;
+use std::path::PathBuf;
 use std::process::ExitCode;
 use std::str::FromStr;
 use std::time::{Duration as StdDuration, Instant};
 
-use alarm::audio::AlarmSound;
-use alarm::{Alarms, Event, Subscriber};
+use alarm::Event;
+use alarm::audio::{self, AlarmSound, RampShape, SOUND_METADATA_KEY, VolumeRamp};
+use alarm::client::{BusAddress, Client};
+use alarm::error::Error;
+use alarm::vibration::{Vibration, VibrationPattern};
 use clap::{Args, Parser, Subcommand};
 use rezz::Alarm;
+use rezz::recurrence::Recurrence;
 use time::error::ComponentRange;
 use time::format_description::well_known::Rfc2822;
 use time::{Duration, Month, OffsetDateTime, Time, UtcOffset};
 use uuid::Uuid;
 
+/// Exit code when an alarm with the requested ID exists already.
+const EXIT_ALARM_EXISTS: u8 = 10;
+
+/// Exit code when no alarm with the requested ID exists.
+const EXIT_ALARM_NOT_FOUND: u8 = 11;
+
+/// Exit code when the rezz daemon is not running.
+const EXIT_REZZ_UNAVAILABLE: u8 = 12;
+
 /// Infinite sleep timeout.
 const INFINITY: StdDuration = StdDuration::from_secs(60 * 60 * 24 * 365 * 999);
 
 #[derive(Parser, Debug)]
 #[command(author, version, about)]
 struct Cli {
+    /// DBus message bus of the rezz daemon (system, session, or a bus address).
+    #[clap(long, global = true, default_value = "system")]
+    bus: BusAddress,
+
     #[command(subcommand)]
     subcommand: Subcmd,
 }
@@ -38,10 +56,52 @@ enum Subcmd {
     /// List all alarms.
     #[clap(alias = "l")]
     List(ListArgs),
+    /// Enable disabled alarms.
+    #[clap(alias = "e")]
+    Enable(ToggleArgs),
+    /// Disable alarms without removing them.
+    Disable(ToggleArgs),
+    /// Snooze a ringing alarm.
+    #[clap(alias = "s")]
+    Snooze(SnoozeArgs),
 }
 
 #[derive(Args, Debug)]
-struct DaemonArgs {}
+struct DaemonArgs {
+    /// Volume percentage at the start of the alarm.
+    #[clap(long, default_value_t = 100)]
+    volume_start: u8,
+    /// Volume percentage at the end of the volume ramp.
+    #[clap(long, default_value_t = 100)]
+    volume_target: u8,
+    /// Seconds until the target volume is reached.
+    #[clap(long, default_value_t = 0)]
+    ramp_seconds: u64,
+    /// Volume ramp curve (linear, logarithmic).
+    #[clap(long, default_value_t)]
+    ramp_shape: RampShape,
+    /// Vibration pattern of alternating on/off milliseconds (e.g. 500,500).
+    #[clap(long)]
+    vibrate: Option<VibrationPattern>,
+    /// Force feedback device used for vibration [default: autodetect].
+    #[clap(long)]
+    vibration_device: Option<PathBuf>,
+    /// Disable the alarm sound, for vibration-only alarms.
+    #[clap(long)]
+    silent: bool,
+}
+
+impl DaemonArgs {
+    /// Get the alarm's volume ramp.
+    fn volume_ramp(&self) -> VolumeRamp {
+        VolumeRamp {
+            duration: StdDuration::from_secs(self.ramp_seconds),
+            target: self.volume_target,
+            start: self.volume_start,
+            shape: self.ramp_shape,
+        }
+    }
+}
 
 #[derive(Args, Debug)]
 struct AddArgs {
@@ -53,6 +113,19 @@ struct AddArgs {
     /// Seconds to ring the alarm for.
     #[clap(long, short = 's', default_value_t = 600)]
     ring_seconds: u32,
+    /// Recurrence rule (daily, weekly:mon-fri, interval:2, monthly:1:mon).
+    #[clap(long, short = 'r', default_value_t)]
+    repeat: Recurrence,
+    /// Human-readable alarm description.
+    #[clap(long, short = 'l', default_value = "")]
+    label: String,
+    /// Additional metadata in KEY=VALUE format.
+    #[clap(long, value_parser = parse_metadata)]
+    meta: Vec<(String, String)>,
+    /// Sound file, relative to `$XDG_DATA_HOME/aevum/sounds` [default: built-in
+    /// sound].
+    #[clap(long)]
+    sound: Option<String>,
 }
 
 #[derive(Args, Debug)]
@@ -62,43 +135,100 @@ struct RemoveArgs {
 }
 
 #[derive(Args, Debug)]
-struct ListArgs {}
+struct ListArgs {
+    /// Only list alarms which can be modified by the current user.
+    #[clap(long)]
+    owned: bool,
+}
+
+#[derive(Args, Debug)]
+struct ToggleArgs {
+    /// Alarm IDs.
+    #[clap(required = true)]
+    id: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+struct SnoozeArgs {
+    /// Alarm ID.
+    id: String,
+    /// Minutes to delay the alarm by.
+    #[clap(long, short = 'm', default_value_t = 10)]
+    minutes: u32,
+}
 
 #[tokio::main(flavor = "current_thread")]
 pub async fn main() -> ExitCode {
     let cli = Cli::parse();
+    let client = Client::new(cli.bus);
 
     match cli.subcommand {
         Subcmd::Add(args) => {
             let id = args.id.unwrap_or_else(|| Uuid::new_v4().to_string());
             let unix_time = (args.time.0 - OffsetDateTime::UNIX_EPOCH).whole_seconds();
-            let alarm = Alarm::new(&id, unix_time, args.ring_seconds);
+            let mut alarm = Alarm::new(&id, unix_time, args.ring_seconds);
+            alarm.recurrence = args.repeat;
+            alarm.label = args.label;
+            alarm.metadata = args.meta.into_iter().collect();
+            if let Some(sound) = args.sound {
+                alarm.metadata.insert(SOUND_METADATA_KEY.into(), sound);
+            }
 
-            match Alarms.add(alarm).await {
+            match client.add(alarm).await {
                 Ok(()) => println!("Added alarm with ID {id:?}"),
                 Err(err) => {
                     eprintln!("Could not add alarm: {err}");
-                    return ExitCode::from(1);
+                    return exit_code(&err, 1);
                 },
             }
         },
         Subcmd::Remove(args) => {
             for id in &args.id {
-                match Alarms.remove(id.clone()).await {
+                match client.remove(id.clone()).await {
                     Ok(()) => println!("Removed alarm with ID {:?}", args.id),
                     Err(err) => {
                         eprintln!("Could not remove alarm: {err}");
-                        return ExitCode::from(2);
+                        return exit_code(&err, 2);
+                    },
+                }
+            }
+        },
+        Subcmd::Enable(args) => {
+            for id in args.id {
+                match client.set_enabled(id.clone(), true).await {
+                    Ok(()) => println!("Enabled alarm with ID {id:?}"),
+                    Err(err) => {
+                        eprintln!("Could not enable alarm: {err}");
+                        return exit_code(&err, 5);
                     },
                 }
             }
         },
-        Subcmd::List(_args) => {
-            let alarms = match Alarms.load().await {
+        Subcmd::Disable(args) => {
+            for id in args.id {
+                match client.set_enabled(id.clone(), false).await {
+                    Ok(()) => println!("Disabled alarm with ID {id:?}"),
+                    Err(err) => {
+                        eprintln!("Could not disable alarm: {err}");
+                        return exit_code(&err, 5);
+                    },
+                }
+            }
+        },
+        Subcmd::Snooze(args) => match client.snooze(args.id.clone(), args.minutes).await {
+            Ok(()) => println!("Snoozed alarm with ID {:?} for {} minutes", args.id, args.minutes),
+            Err(err) => {
+                eprintln!("Could not snooze alarm: {err}");
+                return exit_code(&err, 4);
+            },
+        },
+        Subcmd::List(args) => {
+            let alarms = if args.owned { client.load_owned().await } else { client.load().await };
+            let alarms = match alarms {
                 Ok(alarms) => alarms,
                 Err(err) => {
                     eprintln!("Could not read alarms database: {err}");
-                    return ExitCode::from(3);
+                    return exit_code(&err, 3);
                 },
             };
 
@@ -109,23 +239,30 @@ pub async fn main() -> ExitCode {
             }
 
             // Print header.
-            println!("\x1b[4;1m{: <36}  {: <31}\x1b[0m", "ID", "Alarm Time");
+            println!(
+                "\x1b[4;1m{: <36}  {: <31}  {: <8}  {: <20}  {: <20}\x1b[0m",
+                "ID", "Alarm Time", "State", "Repeat", "Label"
+            );
 
             // Print each alarm.
             for alarm in alarms {
                 // Try to convert unix seconds to local time.
-                let mut time = OffsetDateTime::UNIX_EPOCH + Duration::seconds(alarm.unix_time);
+                let mut time = OffsetDateTime::UNIX_EPOCH + Duration::seconds(alarm.ring_time());
                 if let Ok(offset) = UtcOffset::current_local_offset() {
                     time = time.to_offset(offset);
                 }
                 let time_str = time.format(&Rfc2822).unwrap();
+                let state = if alarm.enabled { "enabled" } else { "disabled" };
 
-                println!("{: <36}  {: <31}", alarm.id, time_str);
+                println!(
+                    "{: <36}  {: <31}  {: <8}  {: <20}  {: <20}",
+                    alarm.id, time_str, state, alarm.recurrence, alarm.label
+                );
             }
         },
-        Subcmd::Daemon(_args) => {
+        Subcmd::Daemon(args) => {
             // Setup listener for DBus events.
-            let mut subscriber = match Subscriber::new().await {
+            let mut subscriber = match client.subscribe().await {
                 Ok(subscriber) => subscriber,
                 Err(err) => {
                     eprintln!("Could not subscribe to DBus events: {err}");
@@ -135,6 +272,10 @@ pub async fn main() -> ExitCode {
 
             println!("Successfully started alarm daemon");
 
+            if !subscriber.connected() {
+                eprintln!("Rezz is not running, waiting for it to start");
+            }
+
             let mut ringing_alarm: Option<RingingAlarm> = None;
             loop {
                 // Convert optional timeout into infinite future on `None`.
@@ -151,45 +292,65 @@ pub async fn main() -> ExitCode {
                             tokio::time::sleep(StdDuration::from_secs(3)).await;
 
                             // Get full list of alarms, including alarms currently ringing.
-                            let all_alarms = match Alarms.load().await {
+                            let all_alarms = match client.load().await {
                                 Ok(all_alarms) => all_alarms,
                                 Err(_) => continue,
                             };
 
                             // Ignore alarm if it has been picked up while waiting.
-                            if !all_alarms.iter().any(|a| a.id == alarm.id) {
+                            let pending = all_alarms
+                                .iter()
+                                .any(|a| a.id == alarm.id && a.ring_time() == alarm.ring_time());
+                            if !pending {
                                 continue;
                             }
 
                             // Start ringing if the alarm hasn't been picked up by a UI.
-                            let sound = match AlarmSound::play() {
-                                Ok(sound) => sound,
-                                Err(err) => {
-                                    eprintln!("Could not play alarm sound: {err}");
-                                    continue;
-                                },
+                            let sound = if args.silent {
+                                None
+                            } else {
+                                let sound = alarm.metadata.get(SOUND_METADATA_KEY);
+                                let sound = sound.and_then(|sound| audio::sound_path(sound));
+                                AlarmSound::play(sound.as_deref(), args.volume_ramp())
+                                    .inspect_err(|err| eprintln!("Could not play alarm sound: {err}"))
+                                    .ok()
                             };
 
+                            // Start vibrating.
+                            let vibration = args.vibrate.clone().and_then(|pattern| {
+                                Vibration::start(pattern, args.vibration_device.as_deref())
+                                    .inspect_err(|err| eprintln!("Could not vibrate: {err}"))
+                                    .ok()
+                            });
+
+                            if sound.is_none() && vibration.is_none() {
+                                continue;
+                            }
+
                             // Schedule alarm for cancellation.
                             let timeout =
                                 Instant::now() + StdDuration::from_secs(alarm.ring_seconds as u64);
-                            ringing_alarm = Some(RingingAlarm { alarm, timeout, sound });
+                            ringing_alarm = Some(RingingAlarm { alarm, timeout, sound, vibration });
                         },
                         // Cancel alarm if it was removed by a third-party client.
                         Event::AlarmsChanged(alarms) => {
                             if let Some(ringing) = &ringing_alarm
-                                && !alarms.iter().any(|alarm| alarm.id == ringing.alarm.id)
+                                && !alarms.iter().any(|alarm| {
+                                    alarm.enabled
+                                        && alarm.id == ringing.alarm.id
+                                        && alarm.ring_time() == ringing.alarm.ring_time()
+                                })
                             {
                                 ringing_alarm = None;
                             }
                         },
+                        Event::Connected => println!("Connected to rezz"),
+                        Event::Disconnected => eprintln!("Lost connection to rezz"),
                     },
                     // NOTE: This usually isn't hit, since the removal of the Alarm through DBus
                     // after it stops ringing will cancel the alarm automatically.
                     _ = ringing_timeout => {
-                        if let Some(ringing) = ringing_alarm.take() {
-                            ringing.sound.stop();
-                        }
+                        ringing_alarm = None;
                     },
                 }
             }
@@ -199,6 +360,18 @@ pub async fn main() -> ExitCode {
     ExitCode::SUCCESS
 }
 
+/// Get the process exit code for an alarm error.
+///
+/// Errors without a dedicated exit code use the subcommand's `fallback`.
+fn exit_code(err: &Error, fallback: u8) -> ExitCode {
+    match err {
+        Error::AlarmExists(_) => ExitCode::from(EXIT_ALARM_EXISTS),
+        Error::AlarmNotFound(_) => ExitCode::from(EXIT_ALARM_NOT_FOUND),
+        Error::RezzUnavailable => ExitCode::from(EXIT_REZZ_UNAVAILABLE),
+        _ => ExitCode::from(fallback),
+    }
+}
+
 /// DateTime wrapper with `FromStr` implementation.
 #[derive(Clone, Debug)]
 struct ClapDateTime(OffsetDateTime);
@@ -258,11 +431,22 @@ impl FromStr for ClapDateTime {
     }
 }
 
+/// Parse a `KEY=VALUE` metadata pair.
+fn parse_metadata(pair: &str) -> Result<(String, String), String> {
+    match pair.split_once('=') {
+        Some((key, value)) => Ok((key.into(), value.into())),
+        None => Err(format!("missing '=' in {pair:?}")),
+    }
+}
+
 /// Actively ringing alarm.
 struct RingingAlarm {
     alarm: Alarm,
     timeout: Instant,
-    sound: AlarmSound,
+    #[allow(unused)]
+    sound: Option<AlarmSound>,
+    #[allow(unused)]
+    vibration: Option<Vibration>,
 }
 
 #[derive(thiserror::Error, Clone, Debug)]
@@ -0,0 +1,135 @@
+//! Wall clock abstraction.
+
+use std::sync::Mutex;
+
+use time::{Duration, OffsetDateTime, PrimitiveDateTime, UtcOffset};
+
+/// Source of the current time.
+pub trait Clock: Send + Sync {
+    /// Get the current time.
+    fn now(&self) -> OffsetDateTime;
+
+    /// Get the local UTC offset at a specific time.
+    fn local_offset_at(&self, time: OffsetDateTime) -> UtcOffset;
+
+    /// Get the current time in seconds since the unix epoch.
+    fn unix_now(&self) -> i64 {
+        self.now().unix_timestamp()
+    }
+
+    /// Get the current time in the local UTC offset.
+    fn now_local(&self) -> OffsetDateTime {
+        let now = self.now();
+        now.to_offset(self.local_offset_at(now))
+    }
+
+    /// Convert a local date and time to an absolute time.
+    ///
+    /// Local times which are skipped by UTC offset changes, like the start of
+    /// DST, are moved forward by the length of the skipped period.
+    fn assume_local(&self, datetime: PrimitiveDateTime) -> OffsetDateTime {
+        let offset = self.local_offset_at(datetime.assume_utc());
+        let time = datetime.assume_offset(offset);
+        datetime.assume_offset(self.local_offset_at(time))
+    }
+}
+
+/// System wall clock.
+#[derive(Default, Copy, Clone, Debug)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> OffsetDateTime {
+        OffsetDateTime::now_utc()
+    }
+
+    fn local_offset_at(&self, time: OffsetDateTime) -> UtcOffset {
+        UtcOffset::local_offset_at(time).unwrap_or(UtcOffset::UTC)
+    }
+}
+
+/// Manually controlled clock.
+///
+/// The time of this clock only changes when it is explicitly modified. Its
+/// local UTC offset defaults to UTC.
+#[derive(Debug)]
+pub struct FakeClock {
+    now: Mutex<OffsetDateTime>,
+    /// Local UTC offset changes, ordered by their start time.
+    offsets: Mutex<Vec<(OffsetDateTime, UtcOffset)>>,
+}
+
+impl FakeClock {
+    pub fn new(now: OffsetDateTime) -> Self {
+        Self { now: Mutex::new(now), offsets: Default::default() }
+    }
+
+    /// Change the current time.
+    pub fn set(&self, now: OffsetDateTime) {
+        *self.now.lock().unwrap() = now;
+    }
+
+    /// Move the current time forward.
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+
+    /// Change the local UTC offset for all times starting at `start`.
+    pub fn set_offset(&self, start: OffsetDateTime, offset: UtcOffset) {
+        let mut offsets = self.offsets.lock().unwrap();
+        offsets.retain(|(existing_start, _)| *existing_start < start);
+        offsets.push((start, offset));
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> OffsetDateTime {
+        *self.now.lock().unwrap()
+    }
+
+    fn local_offset_at(&self, time: OffsetDateTime) -> UtcOffset {
+        let offsets = self.offsets.lock().unwrap();
+        let offset = offsets.iter().rev().find(|(start, _)| *start <= time);
+        offset.map_or(UtcOffset::UTC, |(_, offset)| *offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::{datetime, offset};
+
+    use super::*;
+
+    #[test]
+    fn fake_offsets() {
+        let clock = FakeClock::new(datetime!(2025-03-30 00:30 UTC));
+        clock.set_offset(OffsetDateTime::UNIX_EPOCH, offset!(+1));
+        clock.set_offset(datetime!(2025-03-30 01:00 UTC), offset!(+2));
+
+        assert_eq!(clock.now_local(), datetime!(2025-03-30 01:30 +1));
+
+        clock.advance(Duration::hours(1));
+        assert_eq!(clock.now_local(), datetime!(2025-03-30 03:30 +2));
+    }
+
+    #[test]
+    fn assume_local_dst() {
+        let clock = FakeClock::new(datetime!(2025-03-29 12:00 UTC));
+        clock.set_offset(OffsetDateTime::UNIX_EPOCH, offset!(+1));
+        clock.set_offset(datetime!(2025-03-30 01:00 UTC), offset!(+2));
+        clock.set_offset(datetime!(2025-10-26 01:00 UTC), offset!(+1));
+
+        // Regular times on both sides of the transition.
+        assert_eq!(clock.assume_local(datetime!(2025-03-30 01:30)), datetime!(2025-03-30 01:30 +1));
+        assert_eq!(clock.assume_local(datetime!(2025-03-30 07:00)), datetime!(2025-03-30 07:00 +2));
+
+        // Skipped time during spring forward.
+        assert_eq!(clock.assume_local(datetime!(2025-03-30 02:30)), datetime!(2025-03-30 03:30 +2));
+
+        // Repeated time during fall back.
+        let repeated = clock.assume_local(datetime!(2025-10-26 02:30));
+        assert!(
+            [datetime!(2025-10-26 02:30 +2), datetime!(2025-10-26 02:30 +1)].contains(&repeated)
+        );
+    }
+}
@@ -0,0 +1,202 @@
+//! Daemon configuration.
+
+use std::fs;
+use std::io::Error as IoError;
+use std::num::NonZeroU64;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use clap::{Parser, ValueEnum};
+use rezz::server::DEFAULT_MAX_SNOOZES;
+use rezz::wakeup::{Mock, Rtc, Sysfs, TimerFd, WakeupBackend};
+use serde::{Deserialize, Deserializer};
+use tracing_subscriber::filter::LevelFilter;
+
+/// Default configuration file location.
+const CONFIG_PATH: &str = "/etc/rezz/rezz.toml";
+
+/// Default database location.
+const DB_PATH: &str = "/var/lib/rezz/alarms.db";
+
+/// Default update frequency in seconds on systems without logind.
+const POLL_INTERVAL: NonZeroU64 = NonZeroU64::new(60 * 5).unwrap();
+
+/// DBus RTC alarm clock daemon.
+#[derive(Parser, Debug)]
+#[command(author, version, about)]
+struct Options {
+    /// Configuration file path [default: /etc/rezz/rezz.toml].
+    #[clap(long, short)]
+    config: Option<PathBuf>,
+    /// Alarm database path.
+    #[clap(long)]
+    db_path: Option<PathBuf>,
+    /// RTC device path.
+    #[clap(long)]
+    rtc_device: Option<PathBuf>,
+    /// System wakeup scheduling backend.
+    #[clap(long, value_enum)]
+    wakeup_backend: Option<Backend>,
+    /// Seconds between RTC updates on systems without logind.
+    #[clap(long)]
+    poll_interval: Option<NonZeroU64>,
+    /// Maximum log level (off, error, warn, info, debug, trace).
+    #[clap(long)]
+    log_level: Option<LevelFilter>,
+    /// DBus message bus the daemon is served on.
+    #[clap(long, value_enum)]
+    bus: Option<Bus>,
+    /// Maximum number of times an alarm can be snoozed.
+    #[clap(long)]
+    max_snoozes: Option<u32>,
+    /// Allow polkit to authorize modifications of other users' alarms.
+    #[clap(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    polkit: Option<bool>,
+}
+
+/// Daemon configuration.
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Alarm database path.
+    pub db_path: PathBuf,
+    /// RTC device path.
+    pub rtc_device: PathBuf,
+    /// System wakeup scheduling backend.
+    pub wakeup_backend: Backend,
+    /// Seconds between RTC updates on systems without logind.
+    pub poll_interval: NonZeroU64,
+    /// Maximum log level.
+    #[serde(deserialize_with = "deserialize_level")]
+    pub log_level: LevelFilter,
+    /// DBus message bus the daemon is served on.
+    pub bus: Bus,
+    /// Maximum number of times an alarm can be snoozed.
+    pub max_snoozes: u32,
+    /// Allow polkit to authorize modifications of other users' alarms.
+    pub polkit: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            db_path: PathBuf::from(DB_PATH),
+            rtc_device: PathBuf::from(rezz::wakeup::RTC_PATH),
+            wakeup_backend: Default::default(),
+            poll_interval: POLL_INTERVAL,
+            log_level: LevelFilter::INFO,
+            bus: Default::default(),
+            max_snoozes: DEFAULT_MAX_SNOOZES,
+            polkit: Default::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Load the configuration from CLI arguments and the configuration file.
+    ///
+    /// CLI arguments take precedence over the configuration file.
+    pub fn load() -> Result<Self, Error> {
+        let options = Options::parse();
+
+        // Read config file, ignoring the default location if it doesn't exist.
+        let mut config = match &options.config {
+            Some(path) => Self::from_file(path)?,
+            None if Path::new(CONFIG_PATH).exists() => Self::from_file(CONFIG_PATH)?,
+            None => Self::default(),
+        };
+
+        // Apply CLI overrides.
+        if let Some(db_path) = options.db_path {
+            config.db_path = db_path;
+        }
+        if let Some(rtc_device) = options.rtc_device {
+            config.rtc_device = rtc_device;
+        }
+        if let Some(wakeup_backend) = options.wakeup_backend {
+            config.wakeup_backend = wakeup_backend;
+        }
+        if let Some(poll_interval) = options.poll_interval {
+            config.poll_interval = poll_interval;
+        }
+        if let Some(log_level) = options.log_level {
+            config.log_level = log_level;
+        }
+        if let Some(bus) = options.bus {
+            config.bus = bus;
+        }
+        if let Some(max_snoozes) = options.max_snoozes {
+            config.max_snoozes = max_snoozes;
+        }
+        if let Some(polkit) = options.polkit {
+            config.polkit = polkit;
+        }
+
+        Ok(config)
+    }
+
+    /// Update frequency on systems without logind.
+    pub fn poll_interval(&self) -> Duration {
+        Duration::from_secs(self.poll_interval.get())
+    }
+
+    /// Create the configured wakeup backend.
+    pub fn wakeup_backend(&self) -> Result<Arc<dyn WakeupBackend>, rezz::Error> {
+        Ok(match self.wakeup_backend {
+            Backend::Rtc => Arc::new(Rtc::new(&self.rtc_device)),
+            Backend::Timerfd => Arc::new(TimerFd::new()?),
+            Backend::Sysfs => Arc::new(Sysfs::for_rtc(&self.rtc_device)?),
+            Backend::Mock => Arc::new(Mock::default()),
+        })
+    }
+
+    /// Parse a TOML configuration file.
+    fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path).map_err(|err| Error::Io(path.into(), err))?;
+        toml::from_str(&content).map_err(|err| Error::Toml(path.into(), err))
+    }
+}
+
+/// DBus message bus.
+#[derive(Deserialize, ValueEnum, Copy, Clone, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Bus {
+    #[default]
+    System,
+    Session,
+}
+
+/// System wakeup scheduling backend.
+#[derive(Deserialize, ValueEnum, Copy, Clone, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// RTC device ioctls.
+    #[default]
+    Rtc,
+    /// `CLOCK_REALTIME_ALARM` timerfd.
+    Timerfd,
+    /// RTC sysfs wakealarm file.
+    Sysfs,
+    /// In-memory backend without system wakeups.
+    Mock,
+}
+
+/// Deserialize a log level from its name.
+fn deserialize_level<'de, D>(deserializer: D) -> Result<LevelFilter, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let level = String::deserialize(deserializer)?;
+    LevelFilter::from_str(&level).map_err(serde::de::Error::custom)
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Could not read {0:?}: {1}")]
+    Io(PathBuf, #[source] IoError),
+    #[error("Invalid config {0:?}: {1}")]
+    Toml(PathBuf, #[source] toml::de::Error),
+}
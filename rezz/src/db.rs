@@ -0,0 +1,202 @@
+//! Alarm database persistence.
+
+use std::fs::{self, File};
+use std::io::{Error as IoError, ErrorKind as IoErrorKind, Write};
+use std::path::PathBuf;
+
+use serde::de::Error as _;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use time::OffsetDateTime;
+use tracing::{error, info};
+
+use crate::Alarm;
+
+/// Current database format version.
+const VERSION: u32 = 1;
+
+/// Versioned database file content.
+#[derive(Deserialize, Serialize)]
+struct Envelope {
+    version: u32,
+    alarms: Vec<Alarm>,
+}
+
+/// Filesystem alarm database.
+pub struct Database {
+    path: PathBuf,
+}
+
+impl Database {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Load all alarms from the database.
+    ///
+    /// Databases which cannot be parsed are moved out of the way, to be
+    /// inspected manually, and an empty list of alarms is returned instead.
+    pub fn load(&self) -> Result<Vec<Alarm>, IoError> {
+        // Ensure DB directory exists.
+        let parent = self.path.parent().ok_or_else(|| {
+            let msg = format!("Invalid DB path: {:?}", self.path);
+            IoError::new(IoErrorKind::InvalidInput, msg)
+        })?;
+        fs::create_dir_all(parent)?;
+
+        // Treat missing or empty databases as empty.
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == IoErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+        if content.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+
+        match Self::parse(&content) {
+            Ok(alarms) => Ok(alarms),
+            // Refuse to touch databases written by a newer version.
+            Err(ParseError::UnsupportedVersion(version)) => {
+                let msg = format!("Unsupported DB version {version} in {:?}", self.path);
+                Err(IoError::new(IoErrorKind::InvalidData, msg))
+            },
+            Err(ParseError::Json(err)) => {
+                let quarantine = self.quarantine()?;
+                error!("Corrupt alarm DB moved to {quarantine:?}: {err}");
+                Ok(Vec::new())
+            },
+        }
+    }
+
+    /// Atomically replace the database content.
+    pub fn write(&self, alarms: &[Alarm]) -> Result<(), IoError> {
+        let envelope = Envelope { version: VERSION, alarms: alarms.to_vec() };
+        let json = serde_json::to_string(&envelope)?;
+
+        // Write alarms to a temporary file.
+        let tmp_path = self.sibling_path("tmp");
+        let mut tmp_file = File::create(&tmp_path)?;
+        tmp_file.write_all(json.as_bytes())?;
+        tmp_file.sync_all()?;
+
+        // Replace the old DB and ensure the rename is persisted.
+        fs::rename(&tmp_path, &self.path)?;
+        if let Some(parent) = self.path.parent() {
+            File::open(parent)?.sync_all()?;
+        }
+
+        Ok(())
+    }
+
+    /// Parse database content, migrating it to the current version.
+    fn parse(content: &str) -> Result<Vec<Alarm>, ParseError> {
+        let mut value: Value = serde_json::from_str(content)?;
+
+        // Apply all migrations necessary to reach the current version.
+        loop {
+            let version = match &value {
+                // Version 0 is a bare array of alarms.
+                Value::Array(_) => 0,
+                Value::Object(object) => match object.get("version").and_then(Value::as_u64) {
+                    Some(version) => version as u32,
+                    None => return Err(serde_json::Error::missing_field("version").into()),
+                },
+                _ => {
+                    let msg = "expected alarm array or object";
+                    return Err(serde_json::Error::custom(msg).into());
+                },
+            };
+
+            value = match version {
+                0 => serde_json::json!({ "version": 1, "alarms": value }),
+                VERSION => break,
+                version => return Err(ParseError::UnsupportedVersion(version)),
+            };
+
+            info!("Migrated alarm DB from version {version}");
+        }
+
+        let envelope: Envelope = serde_json::from_value(value)?;
+        Ok(envelope.alarms)
+    }
+
+    /// Move the database file out of the way.
+    ///
+    /// Returns the new location of the database file.
+    fn quarantine(&self) -> Result<PathBuf, IoError> {
+        let unix_time = OffsetDateTime::now_utc().unix_timestamp();
+        let quarantine_path = self.sibling_path(&format!("corrupt-{unix_time}"));
+        fs::rename(&self.path, &quarantine_path)?;
+        Ok(quarantine_path)
+    }
+
+    /// Get a path next to the database with an additional extension.
+    fn sibling_path(&self, extension: &str) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(".");
+        path.push(extension);
+        path.into()
+    }
+}
+
+/// Database parsing errors.
+#[derive(thiserror::Error, Debug)]
+enum ParseError {
+    #[error("{0}")]
+    Json(#[from] serde_json::Error),
+    #[error("unsupported version {0}")]
+    UnsupportedVersion(u32),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    /// Create a database in a new temporary directory.
+    fn test_db(name: &str) -> Database {
+        Database::new(testing::temp_dir(&format!("db-{name}")).join("alarms.db"))
+    }
+
+    #[test]
+    fn roundtrip() {
+        let db = test_db("roundtrip");
+        assert_eq!(db.load().unwrap(), Vec::new());
+
+        let alarms = vec![Alarm::new("a", 1, 2), Alarm::new("b", 3, 4)];
+        db.write(&alarms).unwrap();
+        assert_eq!(db.load().unwrap(), alarms);
+        assert!(!db.sibling_path("tmp").exists());
+    }
+
+    #[test]
+    fn migrate_bare_array() {
+        let db = test_db("migrate");
+        fs::write(&db.path, r#"[{"id":"a","unix_time":1,"ring_seconds":2}]"#).unwrap();
+        assert_eq!(db.load().unwrap(), vec![Alarm::new("a", 1, 2)]);
+    }
+
+    #[test]
+    fn quarantine_corrupt() {
+        let db = test_db("corrupt");
+        fs::write(&db.path, r#"[{"id":"a","unix_"#).unwrap();
+        assert_eq!(db.load().unwrap(), Vec::new());
+        assert!(!db.path.exists());
+
+        let dir = db.path.parent().unwrap();
+        let quarantined = fs::read_dir(dir)
+            .unwrap()
+            .filter_map(Result::ok)
+            .any(|entry| entry.file_name().to_string_lossy().starts_with("alarms.db.corrupt-"));
+        assert!(quarantined);
+    }
+
+    #[test]
+    fn reject_newer_version() {
+        let db = test_db("version");
+        fs::write(&db.path, r#"{"version":999,"alarms":[]}"#).unwrap();
+        assert!(db.load().is_err());
+        assert!(db.path.exists());
+    }
+}
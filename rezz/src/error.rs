@@ -0,0 +1,54 @@
+//! Rezz DBus errors.
+//!
+//! This is shared between the rezz daemon and its clients.
+
+use zbus::fdo;
+
+/// Errors returned by the alarm DBus interface.
+#[derive(zbus::DBusError, Debug)]
+#[zbus(prefix = "org.catacombing.rezz.Error")]
+pub enum DBusError {
+    #[zbus(error)]
+    ZBus(zbus::Error),
+    /// Alarm finished ringing before it was submitted.
+    ElapsedTime(String),
+    /// Alarm ring duration exceeds
+    /// [`MAX_RING_SECONDS`](crate::server::MAX_RING_SECONDS).
+    RingDuration(String),
+    /// Alarm ID exceeds [`MAX_ID_LENGTH`](crate::server::MAX_ID_LENGTH).
+    IdLength(String),
+    /// Alarm label exceeds
+    /// [`MAX_LABEL_LENGTH`](crate::server::MAX_LABEL_LENGTH).
+    LabelLength(String),
+    /// Alarm metadata exceeds
+    /// [`MAX_METADATA_ENTRIES`](crate::server::MAX_METADATA_ENTRIES) or
+    /// [`MAX_METADATA_BYTES`](crate::server::MAX_METADATA_BYTES).
+    MetadataSize(String),
+    /// Caller owns [`MAX_ALARMS_PER_USER`](crate::server::MAX_ALARMS_PER_USER)
+    /// alarms already.
+    TooManyAlarms(String),
+    /// Alarm was snoozed the maximum number of times already.
+    SnoozeLimit(String),
+    /// An alarm with this ID exists already.
+    ///
+    /// Contains the alarm's ID.
+    AlarmExists(String),
+    /// No alarm with this ID exists.
+    ///
+    /// Contains the alarm's ID.
+    AlarmNotFound(String),
+    InvalidArgs(String),
+    AccessDenied(String),
+    Failed(String),
+}
+
+impl From<fdo::Error> for DBusError {
+    fn from(err: fdo::Error) -> Self {
+        match err {
+            fdo::Error::InvalidArgs(msg) => Self::InvalidArgs(msg),
+            fdo::Error::AccessDenied(msg) => Self::AccessDenied(msg),
+            fdo::Error::Failed(msg) => Self::Failed(msg),
+            err => Self::ZBus(err.into()),
+        }
+    }
+}
@@ -1,19 +1,30 @@
 use std::cmp::Ordering;
-use std::fs::File;
+use std::collections::HashMap;
 use std::io;
-use std::mem::MaybeUninit;
-use std::os::fd::AsRawFd;
 
 use serde::{Deserialize, Serialize};
-use time::OffsetDateTime;
+use time::{OffsetDateTime, PrimitiveDateTime};
 use zbus::zvariant::{OwnedValue, Type, Value};
 
-use crate::ioctl::RtcWkalm;
+use crate::clock::Clock;
+use crate::recurrence::Recurrence;
 
+pub mod clock;
+mod db;
+pub mod error;
 mod ioctl;
+mod logind;
+mod polkit;
+pub mod recurrence;
+pub mod server;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod wakeup;
 
-/// Primary RTC path, should always exist for systems with RTC.
-const RTC_PATH: &str = "/dev/rtc";
+/// Owner of alarms created before ownership was recorded.
+///
+/// Rezz assigns these alarms to root when loading them from the database.
+pub const UNOWNED: u32 = u32::MAX;
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -21,46 +32,109 @@ pub enum Error {
     Nix(#[from] nix::Error),
     #[error("{0}")]
     Io(#[from] io::Error),
-}
-
-/// Set a new RTC wakeup time.
-pub fn set_wakeup(time: OffsetDateTime) -> Result<(), Error> {
-    let rtc_file = File::open(RTC_PATH)?;
-    unsafe { ioctl::rtc_wkalm_set(rtc_file.as_raw_fd(), &time.into() as *const _)? };
-    Ok(())
-}
-
-pub fn get_wakeup() -> Result<Option<OffsetDateTime>, Error> {
-    let rtc_file = File::open(RTC_PATH)?;
-    let mut time: MaybeUninit<RtcWkalm> = MaybeUninit::uninit();
-    let time = unsafe {
-        ioctl::rtc_wkalm_rd(rtc_file.as_raw_fd(), time.as_mut_ptr())?;
-        time.assume_init()
-    };
-    Ok(time.into())
-}
-
-/// Clear all current wakeup times.
-pub fn clear_wakeup() -> Result<(), Error> {
-    let rtc_file = File::open(RTC_PATH)?;
-    unsafe { ioctl::rtc_wkalm_set(rtc_file.as_raw_fd(), &ioctl::RESET_ALARM as *const _)? };
-    Ok(())
+    #[error("{0}")]
+    ZBus(#[from] zbus::Error),
 }
 
 /// Single alarm.
 #[derive(Deserialize, Serialize, Type, Value, OwnedValue, Clone, PartialEq, Eq, Debug)]
 pub struct Alarm {
     pub id: String,
+    /// Time of the next occurrence.
     pub unix_time: i64,
     pub ring_seconds: u32,
+    #[serde(default)]
+    pub recurrence: Recurrence,
+    /// Time the current occurrence was snoozed until, `0` if not snoozed.
+    #[serde(default)]
+    pub snoozed_until: i64,
+    /// Number of times the current occurrence was snoozed.
+    #[serde(default)]
+    pub snoozes: u32,
+    /// Human-readable description, empty if unlabeled.
+    #[serde(default)]
+    pub label: String,
+    /// Arbitrary client-defined key/value pairs.
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+    /// Whether the alarm will ring, disabled alarms are kept without ringing.
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// UID of the user which created the alarm.
+    ///
+    /// This is assigned by the server and ignored when sent by clients.
+    #[serde(default = "default_owner")]
+    pub owner: u32,
 }
 
 impl Alarm {
     pub fn new(id: impl Into<String>, unix_time: i64, ring_seconds: u32) -> Self {
-        Self { id: id.into(), unix_time, ring_seconds }
+        Self {
+            unix_time,
+            ring_seconds,
+            id: id.into(),
+            recurrence: Default::default(),
+            snoozed_until: Default::default(),
+            snoozes: Default::default(),
+            label: Default::default(),
+            metadata: Default::default(),
+            enabled: true,
+            owner: UNOWNED,
+        }
+    }
+
+    /// Time at which the alarm will start ringing.
+    ///
+    /// This is the snooze deadline for snoozed alarms and the time of the next
+    /// occurrence otherwise.
+    pub fn ring_time(&self) -> i64 {
+        match self.snoozed_until {
+            0 => self.unix_time,
+            snoozed_until => snoozed_until,
+        }
+    }
+
+    /// Get the first occurrence after the unix timestamp `after`.
+    ///
+    /// The current [`Self::unix_time`] is used as the anchor for the
+    /// alarm's [`Recurrence`], so this will never return a time before it.
+    ///
+    /// Occurrences keep their local time of day across UTC offset changes.
+    pub fn next_occurrence(&self, after: i64, clock: &dyn Clock) -> Option<i64> {
+        let anchor = OffsetDateTime::from_unix_timestamp(self.unix_time).ok()?;
+
+        // Keep one-time alarms at their exact time, even in ambiguous local hours.
+        if self.recurrence == Recurrence::Once {
+            return (self.unix_time > after).then_some(self.unix_time);
+        }
+
+        let anchor = anchor.to_offset(clock.local_offset_at(anchor));
+        let mut after = OffsetDateTime::from_unix_timestamp(after).ok()?;
+
+        loop {
+            let next = self.recurrence.next(anchor, after)?;
+
+            // Move occurrence to its local time in the offset of its date.
+            let local = clock.assume_local(PrimitiveDateTime::new(next.date(), next.time()));
+            if local > after {
+                return Some(local.unix_timestamp());
+            }
+
+            after = next;
+        }
     }
 }
 
+/// Default for alarms stored before they could be disabled.
+fn default_enabled() -> bool {
+    true
+}
+
+/// Default for alarms stored before ownership was recorded.
+fn default_owner() -> u32 {
+    UNOWNED
+}
+
 impl PartialOrd for Alarm {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
@@ -69,7 +143,7 @@ impl PartialOrd for Alarm {
 
 impl Ord for Alarm {
     fn cmp(&self, other: &Self) -> Ordering {
-        match self.unix_time.cmp(&other.unix_time) {
+        match self.ring_time().cmp(&other.ring_time()) {
             Ordering::Equal => match self.id.cmp(&other.id) {
                 Ordering::Equal => self.ring_seconds.cmp(&other.ring_seconds),
                 ordering => ordering,
@@ -1,14 +1,27 @@
-use tracing::{Level, subscriber};
+use std::process;
+
+use tracing::subscriber;
 use tracing_subscriber::FmtSubscriber;
 
+use crate::config::Config;
+
+mod config;
 mod dbus;
-mod logind;
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() {
+    // Parse CLI arguments and configuration file.
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{err}");
+            process::exit(1);
+        },
+    };
+
     // Setup logging.
-    let subscriber = FmtSubscriber::builder().with_max_level(Level::INFO).finish();
+    let subscriber = FmtSubscriber::builder().with_max_level(config.log_level).finish();
     subscriber::set_global_default(subscriber).expect("setting default subscriber failed");
 
-    dbus::launch().await;
+    dbus::launch(config).await;
 }
@@ -0,0 +1,425 @@
+//! Alarm recurrence rules.
+
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use time::{Date, Duration, Month, OffsetDateTime, PrimitiveDateTime, Weekday};
+use zbus::zvariant::{self, Signature, Type, Value};
+
+/// All weekdays, in order.
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Monday,
+    Weekday::Tuesday,
+    Weekday::Wednesday,
+    Weekday::Thursday,
+    Weekday::Friday,
+    Weekday::Saturday,
+    Weekday::Sunday,
+];
+
+/// Maximum number of months searched for a monthly occurrence.
+///
+/// Since every month has at least four of each weekday and the fifth one
+/// appears multiple times a year, this is always sufficient for valid rules.
+const MAX_MONTHS: usize = 24;
+
+/// Alarm recurrence rule.
+///
+/// The time of day is not part of the rule, it is derived from the previous
+/// occurrence of the alarm instead.
+///
+/// Rules are encoded as strings both in the database and over DBus:
+///  - `once`
+///  - `weekly:mon-fri,sun`
+///  - `interval:2` (every 2 days)
+///  - `monthly:1:mon` (first monday of every month)
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Default, Debug)]
+#[serde(into = "String", try_from = "String")]
+pub enum Recurrence {
+    /// Ring only once.
+    #[default]
+    Once,
+    /// Ring every week on the specified days.
+    Weekly(Weekdays),
+    /// Ring every N days.
+    Interval(u16),
+    /// Ring on the Nth weekday of every month.
+    ///
+    /// Months without an Nth weekday are skipped.
+    Monthly { week: u8, weekday: Weekday },
+}
+
+impl Recurrence {
+    /// Check if the alarm rings more than once.
+    pub fn is_recurring(&self) -> bool {
+        *self != Self::Once
+    }
+
+    /// Get the first occurrence after `after`.
+    ///
+    /// The `anchor` is the previous occurrence of the alarm. Its time of day is
+    /// used for all future occurrences and no occurrence before it will be
+    /// returned. All calculations are done in the anchor's UTC offset.
+    ///
+    /// Returns `None` if the rule has no occurrences after `after`.
+    pub fn next(&self, anchor: OffsetDateTime, after: OffsetDateTime) -> Option<OffsetDateTime> {
+        let offset = anchor.offset();
+        let time = anchor.time();
+        let after = after.to_offset(offset);
+
+        // Get the occurrence on a specific date, if it's a valid candidate.
+        let occurrence = |date: Date| {
+            let occurrence = PrimitiveDateTime::new(date, time).assume_offset(offset);
+            (occurrence >= anchor && occurrence > after).then_some(occurrence)
+        };
+
+        // Skip all dates before the anchor and more than a day before `after`.
+        let start = after.date().previous_day().unwrap_or(Date::MIN).max(anchor.date());
+
+        match self {
+            Self::Once => (anchor > after).then_some(anchor),
+            Self::Weekly(weekdays) if weekdays.is_empty() => None,
+            Self::Weekly(weekdays) => {
+                let mut date = start;
+                loop {
+                    if weekdays.contains(date.weekday())
+                        && let Some(occurrence) = occurrence(date)
+                    {
+                        return Some(occurrence);
+                    }
+                    date = date.next_day()?;
+                }
+            },
+            Self::Interval(0) => None,
+            Self::Interval(days) => {
+                let days = *days as i64;
+
+                // Skip directly to the last interval before the start date.
+                let elapsed = (start - anchor.date()).whole_days();
+                let mut date = anchor.date() + Duration::days(elapsed - elapsed % days);
+
+                loop {
+                    if let Some(occurrence) = occurrence(date) {
+                        return Some(occurrence);
+                    }
+                    date += Duration::days(days);
+                }
+            },
+            Self::Monthly { week: week @ 1..=5, weekday } => {
+                let mut year = start.year();
+                let mut month = start.month();
+
+                for _ in 0..MAX_MONTHS {
+                    if let Some(date) = nth_weekday(year, month, *weekday, *week)
+                        && let Some(occurrence) = occurrence(date)
+                    {
+                        return Some(occurrence);
+                    }
+
+                    if month == Month::December {
+                        year += 1;
+                    }
+                    month = month.next();
+                }
+
+                None
+            },
+            Self::Monthly { .. } => None,
+        }
+    }
+}
+
+impl Display for Recurrence {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Once => write!(f, "once"),
+            Self::Weekly(weekdays) => write!(f, "weekly:{weekdays}"),
+            Self::Interval(days) => write!(f, "interval:{days}"),
+            Self::Monthly { week, weekday } => {
+                write!(f, "monthly:{week}:{}", weekday_name(*weekday))
+            },
+        }
+    }
+}
+
+impl FromStr for Recurrence {
+    type Err = RecurrenceError;
+
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        let invalid = || RecurrenceError::InvalidRule(rule.into());
+
+        let (kind, args) = rule.split_once(':').unwrap_or((rule, ""));
+        match (kind, args) {
+            ("once", "") => Ok(Self::Once),
+            ("daily", "") => Ok(Self::Interval(1)),
+            ("weekly", weekdays) => Ok(Self::Weekly(Weekdays::from_str(weekdays)?)),
+            ("interval", days) => match u16::from_str(days) {
+                Ok(days) if days > 0 => Ok(Self::Interval(days)),
+                _ => Err(invalid()),
+            },
+            ("monthly", args) => {
+                let (week, weekday) = args.split_once(':').ok_or_else(invalid)?;
+                let week = u8::from_str(week).map_err(|_| invalid())?;
+                if !(1..=5).contains(&week) {
+                    return Err(invalid());
+                }
+                let weekday = parse_weekday(weekday)?;
+                Ok(Self::Monthly { week, weekday })
+            },
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl From<Recurrence> for String {
+    fn from(recurrence: Recurrence) -> Self {
+        recurrence.to_string()
+    }
+}
+
+impl TryFrom<String> for Recurrence {
+    type Error = RecurrenceError;
+
+    fn try_from(rule: String) -> Result<Self, Self::Error> {
+        Self::from_str(&rule)
+    }
+}
+
+impl Type for Recurrence {
+    const SIGNATURE: &'static Signature = &Signature::Str;
+}
+
+impl From<Recurrence> for Value<'_> {
+    fn from(recurrence: Recurrence) -> Self {
+        Value::from(recurrence.to_string())
+    }
+}
+
+impl TryFrom<Value<'_>> for Recurrence {
+    type Error = zvariant::Error;
+
+    fn try_from(value: Value<'_>) -> Result<Self, Self::Error> {
+        let rule = String::try_from(value)?;
+        Self::from_str(&rule).map_err(|err| zvariant::Error::Message(err.to_string()))
+    }
+}
+
+/// Set of weekdays.
+#[derive(Copy, Clone, PartialEq, Eq, Default, Debug)]
+pub struct Weekdays(u8);
+
+impl Weekdays {
+    /// Monday to Friday.
+    pub const WORKDAYS: Self = Self(0b0011111);
+
+    /// Check if no weekday is set.
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Check if a weekday is part of the set.
+    pub fn contains(&self, weekday: Weekday) -> bool {
+        self.0 & Self::bit(weekday) != 0
+    }
+
+    /// Add a weekday to the set.
+    pub fn insert(&mut self, weekday: Weekday) {
+        self.0 |= Self::bit(weekday);
+    }
+
+    /// Iterate over all weekdays in the set, starting with monday.
+    pub fn iter(&self) -> impl Iterator<Item = Weekday> + '_ {
+        WEEKDAYS.into_iter().filter(|weekday| self.contains(*weekday))
+    }
+
+    /// Get the bitmask for a weekday.
+    fn bit(weekday: Weekday) -> u8 {
+        1 << weekday.number_days_from_monday()
+    }
+}
+
+impl FromIterator<Weekday> for Weekdays {
+    fn from_iter<I: IntoIterator<Item = Weekday>>(iter: I) -> Self {
+        let mut weekdays = Self::default();
+        for weekday in iter {
+            weekdays.insert(weekday);
+        }
+        weekdays
+    }
+}
+
+impl Display for Weekdays {
+    /// Format weekdays as list, combining consecutive days into ranges.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut ranges: Vec<(Weekday, Weekday)> = Vec::new();
+        for weekday in self.iter() {
+            match ranges.last_mut() {
+                Some((_, end)) if end.next() == weekday && weekday != Weekday::Monday => {
+                    *end = weekday
+                },
+                _ => ranges.push((weekday, weekday)),
+            }
+        }
+
+        let items: Vec<_> = ranges
+            .into_iter()
+            .map(|(start, end)| match start == end {
+                true => weekday_name(start).to_string(),
+                false => format!("{}-{}", weekday_name(start), weekday_name(end)),
+            })
+            .collect();
+        write!(f, "{}", items.join(","))
+    }
+}
+
+impl FromStr for Weekdays {
+    type Err = RecurrenceError;
+
+    /// Parse a comma-separated list of weekdays or weekday ranges.
+    fn from_str(weekdays: &str) -> Result<Self, Self::Err> {
+        let mut set = Self::default();
+
+        for item in weekdays.split(',') {
+            match item.split_once('-') {
+                Some((start, end)) => {
+                    let start = parse_weekday(start)?.number_days_from_monday() as usize;
+                    let end = parse_weekday(end)?.number_days_from_monday() as usize;
+                    if start > end {
+                        return Err(RecurrenceError::InvalidRule(item.into()));
+                    }
+                    WEEKDAYS[start..=end].iter().for_each(|weekday| set.insert(*weekday));
+                },
+                None => set.insert(parse_weekday(item)?),
+            }
+        }
+
+        Ok(set)
+    }
+}
+
+/// Recurrence parsing error.
+#[derive(thiserror::Error, Debug)]
+pub enum RecurrenceError {
+    #[error("invalid recurrence rule: {0:?}")]
+    InvalidRule(String),
+    #[error("invalid weekday: {0:?}")]
+    InvalidWeekday(String),
+}
+
+/// Get the Nth weekday of a month.
+fn nth_weekday(year: i32, month: Month, weekday: Weekday, n: u8) -> Option<Date> {
+    let first = Date::from_calendar_date(year, month, 1).ok()?;
+    let offset =
+        (weekday.number_days_from_monday() + 7 - first.weekday().number_days_from_monday()) % 7;
+    let day = 1 + offset + (n - 1) * 7;
+    Date::from_calendar_date(year, month, day).ok()
+}
+
+/// Parse a weekday from its abbreviated or full name.
+fn parse_weekday(name: &str) -> Result<Weekday, RecurrenceError> {
+    let lowercase = name.trim().to_lowercase();
+    WEEKDAYS
+        .into_iter()
+        .find(|weekday| {
+            let full_name = weekday.to_string().to_lowercase();
+            lowercase.len() >= 3 && full_name.starts_with(&lowercase)
+        })
+        .ok_or_else(|| RecurrenceError::InvalidWeekday(name.into()))
+}
+
+/// Abbreviated weekday name.
+fn weekday_name(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Monday => "mon",
+        Weekday::Tuesday => "tue",
+        Weekday::Wednesday => "wed",
+        Weekday::Thursday => "thu",
+        Weekday::Friday => "fri",
+        Weekday::Saturday => "sat",
+        Weekday::Sunday => "sun",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::{Time, UtcOffset};
+
+    use super::*;
+
+    /// Create a datetime at 06:30 in UTC+2.
+    fn date_time(year: i32, month: Month, day: u8) -> OffsetDateTime {
+        let date = Date::from_calendar_date(year, month, day).unwrap();
+        let time = Time::from_hms(6, 30, 0).unwrap();
+        let offset = UtcOffset::from_hms(2, 0, 0).unwrap();
+        PrimitiveDateTime::new(date, time).assume_offset(offset)
+    }
+
+    #[test]
+    fn weekly() {
+        let rule = Recurrence::from_str("weekly:mon-fri").unwrap();
+        assert_eq!(rule, Recurrence::Weekly(Weekdays::WORKDAYS));
+        assert_eq!(
+            Recurrence::from_str("weekly:sun,mon,tue,thu").unwrap().to_string(),
+            "weekly:mon-tue,thu,sun"
+        );
+
+        // Friday advances to monday.
+        let friday = date_time(2025, Month::October, 17);
+        let next = rule.next(friday, friday);
+        assert_eq!(next, Some(date_time(2025, Month::October, 20)));
+
+        // Anchors far in the past skip to the next occurrence.
+        let anchor = date_time(2020, Month::January, 1);
+        let next = rule.next(anchor, friday + Duration::hours(1));
+        assert_eq!(next, Some(date_time(2025, Month::October, 20)));
+
+        // Unmatched anchors are moved to the first matching day.
+        let saturday = date_time(2025, Month::October, 18);
+        let next = rule.next(saturday, saturday - Duration::seconds(1));
+        assert_eq!(next, Some(date_time(2025, Month::October, 20)));
+    }
+
+    #[test]
+    fn interval() {
+        let rule = Recurrence::from_str("interval:3").unwrap();
+
+        let anchor = date_time(2025, Month::February, 27);
+        let next = rule.next(anchor, anchor);
+        assert_eq!(next, Some(date_time(2025, Month::March, 2)));
+
+        let next = rule.next(anchor, date_time(2025, Month::March, 3));
+        assert_eq!(next, Some(date_time(2025, Month::March, 5)));
+    }
+
+    #[test]
+    fn monthly() {
+        let rule = Recurrence::from_str("monthly:1:monday").unwrap();
+        assert_eq!(rule.to_string(), "monthly:1:mon");
+
+        let anchor = date_time(2025, Month::October, 6);
+        let next = rule.next(anchor, anchor);
+        assert_eq!(next, Some(date_time(2025, Month::November, 3)));
+
+        // Months without a fifth weekday are skipped.
+        let rule = Recurrence::Monthly { week: 5, weekday: Weekday::Friday };
+        let anchor = date_time(2025, Month::October, 31);
+        let next = rule.next(anchor, anchor);
+        assert_eq!(next, Some(date_time(2026, Month::January, 30)));
+    }
+
+    #[test]
+    fn once() {
+        let anchor = date_time(2025, Month::October, 17);
+        assert_eq!(Recurrence::Once.next(anchor, anchor - Duration::seconds(1)), Some(anchor));
+        assert_eq!(Recurrence::Once.next(anchor, anchor), None);
+    }
+
+    #[test]
+    fn invalid_rules() {
+        assert!(Recurrence::from_str("weekly:fri-mon").is_err());
+        assert!(Recurrence::from_str("interval:0").is_err());
+        assert!(Recurrence::from_str("monthly:6:mon").is_err());
+        assert!(Recurrence::from_str("hourly").is_err());
+    }
+}
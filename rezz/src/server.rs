@@ -0,0 +1,1307 @@
+//! DBus RTC wakeup server.
+
+use std::collections::HashMap;
+use std::io::Error as IoError;
+use std::mem;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::time::Duration as StdDuration;
+
+use futures_util::stream::StreamExt;
+use time::{Duration, OffsetDateTime};
+use tokio::sync::{RwLock, watch};
+use tokio::time as tokio_time;
+use tracing::{debug, error, info, warn};
+use zbus::Connection;
+use zbus::fdo::DBusProxy;
+use zbus::message::Header;
+use zbus::names::BusName;
+use zbus::zvariant::{OwnedFd, Value};
+
+use crate::clock::Clock;
+use crate::db::Database;
+use crate::error::DBusError;
+use crate::logind::{ManagerProxy, PrepareForSleepStream};
+use crate::polkit::AuthorityProxy;
+use crate::recurrence::Recurrence;
+use crate::wakeup::WakeupBackend;
+use crate::{Alarm, Error, UNOWNED};
+
+/// Well-known DBus name of the alarm server.
+pub const NAME: &str = "org.catacombing.rezz";
+
+/// DBus object path of the alarm interface.
+pub const PATH: &str = "/org/catacombing/rezz";
+
+/// Infinite sleep timeout.
+const INFINITY: StdDuration = StdDuration::from_secs(60 * 60 * 24 * 365 * 999);
+
+/// Default update frequency on systems without logind.
+const POLL_INTERVAL: StdDuration = StdDuration::from_secs(60 * 5);
+
+/// Time dismissed occurrences stay snoozable without being acknowledged.
+const ACKNOWLEDGE_TIMEOUT: i64 = 60 * 60 * 12;
+
+/// Maximum alarm ring duration in seconds.
+pub const MAX_RING_SECONDS: u32 = 60 * 60;
+
+/// Maximum alarm ID length in bytes.
+pub const MAX_ID_LENGTH: usize = 256;
+
+/// Maximum alarm label length in bytes.
+pub const MAX_LABEL_LENGTH: usize = 256;
+
+/// Maximum number of alarm metadata entries.
+pub const MAX_METADATA_ENTRIES: usize = 32;
+
+/// Maximum combined length of all alarm metadata keys and values in bytes.
+pub const MAX_METADATA_BYTES: usize = 4096;
+
+/// Maximum number of alarms owned by a single user.
+pub const MAX_ALARMS_PER_USER: usize = 100;
+
+/// Default number of times an alarm can be snoozed.
+pub const DEFAULT_MAX_SNOOZES: u32 = 3;
+
+/// Polkit action for modifying alarms owned by other users.
+pub const POLKIT_ACTION: &str = "org.catacombing.rezz.manage-alarms";
+
+/// Polkit flag allowing interactive authentication.
+const POLKIT_ALLOW_USER_INTERACTION: u32 = 1;
+
+/// Alarm DBus server.
+pub struct Server {
+    rezz: Rezz,
+    connection: Connection,
+    logind_connection: Option<Connection>,
+    poll_interval: StdDuration,
+}
+
+impl Server {
+    /// Serve the alarm interface on a DBus connection.
+    ///
+    /// This does not request the [`NAME`] bus name, which is left to the
+    /// caller.
+    pub async fn new(
+        connection: Connection,
+        db_path: impl AsRef<Path>,
+        wakeup: Arc<dyn WakeupBackend>,
+        clock: Arc<dyn Clock>,
+    ) -> Result<Self, Error> {
+        let rezz = Rezz::new(db_path, wakeup, clock)?;
+        connection.object_server().at(PATH, rezz.clone()).await?;
+
+        Ok(Self { rezz, connection, logind_connection: None, poll_interval: POLL_INTERVAL })
+    }
+
+    /// Use logind on this connection for suspend handling.
+    pub fn with_logind(mut self, connection: Connection) -> Self {
+        self.logind_connection = Some(connection);
+        self
+    }
+
+    /// Allow polkit to authorize modifications of other users' alarms.
+    ///
+    /// Without polkit, only root and the owner can modify an alarm.
+    pub fn with_polkit(self, polkit: bool) -> Self {
+        self.rezz.polkit.store(polkit, Ordering::Relaxed);
+        self
+    }
+
+    /// Set the maximum number of times an alarm can be snoozed.
+    pub fn with_max_snoozes(self, max_snoozes: u32) -> Self {
+        self.rezz.max_snoozes.store(max_snoozes, Ordering::Relaxed);
+        self
+    }
+
+    /// Set the update frequency on systems without logind.
+    pub fn with_poll_interval(mut self, poll_interval: StdDuration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Run the server's event loop.
+    pub async fn run(self) {
+        let Self { mut rezz, connection, logind_connection, poll_interval } = self;
+
+        // Immediately cleanup alarms at startup.
+        let mut wait_alarm = tokio_time::sleep(StdDuration::from_secs(0));
+
+        // Get logind suspend stream.
+        let suspend_stream = match &logind_connection {
+            Some(logind_connection) => logind_suspend_stream(logind_connection, &mut rezz).await,
+            None => Err(zbus::Error::Failure("No logind connection".into())),
+        };
+        let mut suspend_stream = match suspend_stream {
+            Ok(suspend_stream) => Some(suspend_stream),
+            Err(err) => {
+                warn!("Running without logind support: {err}");
+                None
+            },
+        };
+
+        // Listen for db changes.
+        let mut alarms_changed = rezz.alarms.read().await.subscribe();
+
+        info!("DBus server started successfully");
+
+        loop {
+            tokio::select! {
+                // Signal alarm changes to DBus clients.
+                _ = alarms_changed.changed() => {
+                    debug!("Alarms changed");
+
+                    let object_server = connection.object_server();
+                    let iface = object_server.interface::<_, Rezz>(PATH).await.unwrap();
+                    let _ = rezz.alarms_changed(iface.signal_emitter()).await;
+                },
+                // Update expired alarms.
+                _ = wait_alarm => debug!("Alarm expired"),
+                // Handle suspend/wakeup.
+                is_suspend = await_suspend(&mut suspend_stream, poll_interval) => {
+                    if is_suspend {
+                        debug!("Handling suspend");
+                        rezz.on_suspend().await;
+                    } else if let Some(logind_connection) = &logind_connection {
+                        debug!("Handling wakeup");
+                        rezz.add_logind_inhibitor(logind_connection).await;
+                    }
+                }
+            }
+
+            // Ensure old alarms are cleaned up.
+            let mut alarms = rezz.alarms.write().await;
+            alarms.remove_elapsed();
+
+            // Update event loop alarm timeout.
+            wait_alarm = match alarms.upcoming() {
+                Some(next_alarm) => {
+                    let alarm_end = next_alarm.ring_time() + next_alarm.ring_seconds as i64;
+                    let seconds = alarm_end.saturating_sub(rezz.clock.unix_now());
+                    tokio_time::sleep(StdDuration::from_secs(seconds as u64))
+                },
+                None => tokio_time::sleep(INFINITY),
+            };
+        }
+    }
+}
+
+/// Get a stream of logind suspend/wakeup events.
+async fn logind_suspend_stream(
+    connection: &Connection,
+    rezz: &mut Rezz,
+) -> zbus::Result<PrepareForSleepStream> {
+    // Setup DBus logind suspend listener.
+    let logind = ManagerProxy::new(connection).await?;
+    let suspend_stream = logind.receive_prepare_for_sleep().await?;
+
+    // Add initial suspend delay inhibitor.
+    rezz.add_logind_inhibitor(connection).await;
+
+    Ok(suspend_stream)
+}
+
+/// Poll the logind suspend stream.
+///
+/// Returns `true` on suspend, `false` on unsuspend.
+///
+/// This will use a fixed timer on systems without logind and will always return
+/// `true`.
+async fn await_suspend(
+    logind_stream: &mut Option<PrepareForSleepStream>,
+    poll_interval: StdDuration,
+) -> bool {
+    match logind_stream {
+        Some(stream) => {
+            let next_event = stream.next().await;
+            next_event
+                .and_then(|event| event.message().body().deserialize::<bool>().ok())
+                .unwrap_or(true)
+        },
+        None => {
+            tokio_time::sleep(poll_interval).await;
+            true
+        },
+    }
+}
+
+/// Register logind inhibitor.
+async fn inhibit(
+    connection: &Connection,
+    what: &str,
+    who: &str,
+    why: &str,
+    mode: &str,
+) -> zbus::Result<OwnedFd> {
+    let logind = ManagerProxy::new(connection).await?;
+    let inhibitor = logind.inhibit(what, who, why, mode).await?;
+    Ok(inhibitor)
+}
+
+struct Rezz {
+    alarms: Arc<RwLock<Store>>,
+    inhibitor: Option<OwnedFd>,
+    wakeup: Arc<dyn WakeupBackend>,
+    clock: Arc<dyn Clock>,
+    polkit: Arc<AtomicBool>,
+    max_snoozes: Arc<AtomicU32>,
+}
+
+impl Clone for Rezz {
+    fn clone(&self) -> Self {
+        Self {
+            alarms: self.alarms.clone(),
+            wakeup: self.wakeup.clone(),
+            polkit: self.polkit.clone(),
+            max_snoozes: self.max_snoozes.clone(),
+            clock: self.clock.clone(),
+            inhibitor: None,
+        }
+    }
+}
+
+impl Rezz {
+    fn new(
+        db: impl AsRef<Path>,
+        wakeup: Arc<dyn WakeupBackend>,
+        clock: Arc<dyn Clock>,
+    ) -> Result<Self, IoError> {
+        let alarms = Arc::new(RwLock::new(Store::new(db, clock.clone())?));
+        Ok(Self {
+            alarms,
+            wakeup,
+            clock,
+            max_snoozes: Arc::new(AtomicU32::new(DEFAULT_MAX_SNOOZES)),
+            inhibitor: Default::default(),
+            polkit: Default::default(),
+        })
+    }
+
+    /// Pre-sleep hook.
+    async fn on_suspend(&mut self) {
+        // Remove outdated alarms.
+        {
+            let mut alarms = self.alarms.write().await;
+            alarms.remove_elapsed();
+        }
+
+        // Ensure next alarm is scheduled.
+        self.schedule_nearest().await;
+
+        // Drop inhibitor to initiate suspend.
+        self.inhibitor.take();
+    }
+
+    /// Update logind sleep delay inhibitor.
+    async fn add_logind_inhibitor(&mut self, connection: &Connection) {
+        let inhibitor = inhibit(connection, "sleep", "Rezz", "RTC clock updates", "delay").await;
+
+        self.inhibitor = match inhibitor {
+            Ok(inhibitor) => Some(inhibitor),
+            Err(err) => {
+                error!("Could not register logind sleep inhibitor: {err}");
+                return;
+            },
+        };
+    }
+
+    /// Ensure the next wakeup is not after the closest alarm.
+    async fn schedule_nearest(&self) {
+        let alarms = self.alarms.read().await;
+
+        // Get nearest alarm.
+        let next_alarm = match alarms.upcoming() {
+            Some(next_alarm) => next_alarm,
+            None => return,
+        };
+
+        // Get staged RTC alarm, if any.
+        let wakeup = match self.wakeup.get() {
+            Ok(wakeup) => wakeup,
+            Err(err) => {
+                error!("Could not read WKALM: {err}");
+                None
+            },
+        };
+
+        // Ignore alarms beyond the scheduled one.
+        let current_time = self.clock.now();
+        let time = OffsetDateTime::UNIX_EPOCH + Duration::seconds(next_alarm.ring_time());
+        if wakeup.is_some_and(|wakeup| wakeup > current_time && time >= wakeup) {
+            return;
+        }
+
+        // Set a new RTC alarm.
+        if let Err(err) = self.wakeup.set(time) {
+            error!("Could set WKALM: {err}");
+        }
+    }
+
+    /// Ensure the caller is allowed to modify an alarm.
+    ///
+    /// Unknown IDs are accepted, leaving it to the caller to report them.
+    async fn authorize(
+        &self,
+        connection: &Connection,
+        header: &Header<'_>,
+        id: &str,
+        action: &str,
+    ) -> Result<(), DBusError> {
+        // Include dismissed alarms, since they can still be snoozed.
+        let owner = {
+            let alarms = self.alarms.read().await;
+            let mut all_alarms = alarms.alarms.iter().chain(&alarms.dismissed);
+            all_alarms.find(|alarm| alarm.id == id).map(|alarm| alarm.owner)
+        };
+        let owner = match owner {
+            Some(owner) => owner,
+            None => return Ok(()),
+        };
+
+        let caller = caller_uid(connection, header).await?;
+        if may_modify(caller, owner) {
+            return Ok(());
+        }
+
+        // Allow administrative overrides through polkit.
+        if self.polkit.load(Ordering::Relaxed) && polkit_authorized(connection, header).await {
+            return Ok(());
+        }
+
+        let msg = format!("Cannot {action} alarm {id:?}: Owned by another user");
+        warn!(msg);
+
+        Err(DBusError::AccessDenied(msg))
+    }
+
+    /// Clear the staged RTC alarm if it matches an alarm's occurrence.
+    async fn unschedule(&self, unix_time: i64) -> Result<(), DBusError> {
+        // Get currently staged RTC alarms.
+        let wakeup = match self.wakeup.get() {
+            Ok(Some(wakeup)) => wakeup,
+            Ok(None) => return Ok(()),
+            Err(err) => {
+                let msg = format!("Could not read WKALM: {err}");
+                error!(msg);
+
+                return Err(DBusError::Failed(msg));
+            },
+        };
+
+        // Ignore if staged RTC alarm does not match the alarm.
+        let time = OffsetDateTime::UNIX_EPOCH + Duration::seconds(unix_time);
+        if time != wakeup {
+            return Ok(());
+        }
+
+        // Clear the staged RTC alarm.
+        if let Err(err) = self.wakeup.clear() {
+            error!("Could not clear WKALM: {err}");
+        }
+
+        // Ensure timely RTC clock updates without logind.
+        self.schedule_nearest().await;
+
+        Ok(())
+    }
+}
+
+#[zbus::interface(name = "org.catacombing.rezz")]
+impl Rezz {
+    async fn add_alarm(
+        &mut self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        mut alarm: Alarm,
+    ) -> Result<(), DBusError> {
+        alarm.owner = caller_uid(connection, &header).await?;
+
+        // Snooze state is managed by the server.
+        alarm.snoozed_until = 0;
+        alarm.snoozes = 0;
+
+        // Move recurring alarms to their first valid occurrence.
+        let after = alarm.unix_time.saturating_sub(1);
+        alarm.unix_time = match alarm.next_occurrence(after, &*self.clock) {
+            Some(unix_time) => unix_time,
+            None => {
+                let msg = format!("Alarm {:?} has no occurrences", alarm.id);
+                error!("Could not add alarm: {msg}");
+
+                return Err(DBusError::InvalidArgs(msg));
+            },
+        };
+
+        if let Err(err) = validate(&alarm, self.clock.unix_now()) {
+            warn!("Could not add alarm: {err}");
+            return Err(err);
+        }
+
+        {
+            let mut alarms = self.alarms.write().await;
+
+            // Prevent individual users from flooding the database.
+            if alarms.owned_count(alarm.owner) >= MAX_ALARMS_PER_USER {
+                let msg = format!("User {} owns {MAX_ALARMS_PER_USER} alarms already", alarm.owner);
+                warn!("Could not add alarm: {msg}");
+
+                return Err(DBusError::TooManyAlarms(msg));
+            }
+
+            let id = alarm.id.clone();
+            if !alarms.add(alarm) {
+                error!("Could not add alarm: ID {id:?} already exists");
+
+                return Err(DBusError::AlarmExists(id));
+            }
+        }
+
+        // Ensure timely RTC clock updates without logind.
+        self.schedule_nearest().await;
+
+        Ok(())
+    }
+
+    /// Replace an existing alarm with the same ID.
+    ///
+    /// The alarm's owner cannot be changed.
+    async fn update_alarm(
+        &mut self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        mut alarm: Alarm,
+    ) -> Result<(), DBusError> {
+        self.authorize(connection, &header, &alarm.id, "update").await?;
+
+        // Move recurring alarms to their first valid occurrence.
+        let after = alarm.unix_time.saturating_sub(1);
+        alarm.unix_time = match alarm.next_occurrence(after, &*self.clock) {
+            Some(unix_time) => unix_time,
+            None => {
+                let msg = format!("Alarm {:?} has no occurrences", alarm.id);
+                error!("Could not update alarm: {msg}");
+
+                return Err(DBusError::InvalidArgs(msg));
+            },
+        };
+
+        let id = alarm.id.clone();
+        let updated = {
+            let mut alarms = self.alarms.write().await;
+
+            // Keep the server's snooze state, unless the schedule was changed.
+            let existing = alarms.alarms.iter().find(|existing| existing.id == id);
+            match existing {
+                Some(existing)
+                    if existing.unix_time == alarm.unix_time
+                        && existing.recurrence == alarm.recurrence =>
+                {
+                    alarm.snoozed_until = existing.snoozed_until;
+                    alarm.snoozes = existing.snoozes;
+                },
+                _ => {
+                    alarm.snoozed_until = 0;
+                    alarm.snoozes = 0;
+                },
+            }
+
+            if let Err(err) = validate(&alarm, self.clock.unix_now()) {
+                warn!("Could not update alarm: {err}");
+                return Err(err);
+            }
+
+            alarms.update(alarm)
+        };
+
+        let previous = match updated {
+            Some(previous) => previous,
+            None => {
+                warn!("Cannot update alarm {id:?}: Invalid ID");
+
+                return Err(DBusError::AlarmNotFound(id));
+            },
+        };
+
+        // Clear the previous wakeup and ensure the new time is scheduled.
+        self.unschedule(previous.ring_time()).await?;
+        self.schedule_nearest().await;
+
+        Ok(())
+    }
+
+    async fn remove_alarm(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        id: String,
+    ) -> Result<(), DBusError> {
+        self.authorize(connection, &header, &id, "remove").await?;
+
+        let removed = {
+            let mut alarms = self.alarms.write().await;
+
+            // Remove alarm from internal cache.
+            match alarms.remove(&id) {
+                Some(alarm) => alarm,
+                None => {
+                    warn!("Cannot remove alarm {id:?}: Invalid ID");
+
+                    return Err(DBusError::AlarmNotFound(id));
+                },
+            }
+        };
+
+        self.unschedule(removed.ring_time()).await
+    }
+
+    /// Enable or disable an alarm without removing it.
+    async fn set_alarm_enabled(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        id: String,
+        enabled: bool,
+    ) -> Result<(), DBusError> {
+        self.authorize(connection, &header, &id, "toggle").await?;
+
+        let previous = {
+            let mut alarms = self.alarms.write().await;
+
+            match alarms.set_enabled(&id, enabled) {
+                Some(alarm) => alarm,
+                None => {
+                    warn!("Cannot toggle alarm {id:?}: Invalid ID");
+
+                    return Err(DBusError::AlarmNotFound(id));
+                },
+            }
+        };
+
+        if enabled {
+            // Ensure timely RTC clock updates without logind.
+            self.schedule_nearest().await;
+            Ok(())
+        } else {
+            self.unschedule(previous.ring_time()).await
+        }
+    }
+
+    /// Finish the current occurrence of an alarm.
+    ///
+    /// Recurring alarms are moved to their next occurrence, while all other
+    /// alarms are removed.
+    async fn dismiss_alarm(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        id: String,
+    ) -> Result<(), DBusError> {
+        self.authorize(connection, &header, &id, "dismiss").await?;
+
+        let dismissed = {
+            let mut alarms = self.alarms.write().await;
+
+            match alarms.dismiss(&id) {
+                Some(alarm) => alarm,
+                None => {
+                    warn!("Cannot dismiss alarm {id:?}: Invalid ID");
+
+                    return Err(DBusError::AlarmNotFound(id));
+                },
+            }
+        };
+
+        self.unschedule(dismissed.ring_time()).await
+    }
+
+    /// Delay a ringing alarm by the specified number of minutes.
+    ///
+    /// This can be used for alarms which are currently ringing, or which have
+    /// been dismissed and would otherwise still be ringing.
+    async fn snooze_alarm(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        id: String,
+        minutes: u32,
+    ) -> Result<(), DBusError> {
+        self.authorize(connection, &header, &id, "snooze").await?;
+
+        if minutes == 0 {
+            let msg = format!("Cannot snooze alarm {id:?}: Invalid duration");
+            warn!(msg);
+
+            return Err(DBusError::InvalidArgs(msg));
+        }
+
+        let snoozed = {
+            let max_snoozes = self.max_snoozes.load(Ordering::Relaxed);
+            let mut alarms = self.alarms.write().await;
+            alarms.snooze(&id, minutes, max_snoozes)
+        };
+
+        match snoozed {
+            Ok(()) => (),
+            Err(SnoozeError::NotRinging) => {
+                warn!("Cannot snooze alarm {id:?}: No ringing alarm with this ID");
+
+                return Err(DBusError::AlarmNotFound(id));
+            },
+            Err(SnoozeError::Limit(max_snoozes)) => {
+                let msg =
+                    format!("Cannot snooze alarm {id:?}: Snoozed {max_snoozes} times already");
+                warn!(msg);
+
+                return Err(DBusError::SnoozeLimit(msg));
+            },
+        }
+
+        // Ensure timely RTC clock updates without logind.
+        self.schedule_nearest().await;
+
+        Ok(())
+    }
+
+    /// Stop ringing a dismissed alarm occurrence.
+    ///
+    /// Dismissed occurrences can be snoozed until they are acknowledged, which
+    /// clients should do once they stopped ringing the alarm.
+    async fn acknowledge_alarm(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        id: String,
+    ) -> Result<(), DBusError> {
+        self.authorize(connection, &header, &id, "acknowledge").await?;
+
+        if !self.alarms.write().await.acknowledge(&id) {
+            warn!("Cannot acknowledge alarm {id:?}: No dismissed alarm with this ID");
+
+            return Err(DBusError::AlarmNotFound(id));
+        }
+
+        Ok(())
+    }
+
+    /// Get all alarms the caller is allowed to modify.
+    async fn owned_alarms(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> Result<Vec<Alarm>, DBusError> {
+        let caller = caller_uid(connection, &header).await?;
+
+        let alarms = self.alarms.read().await;
+        let owned = alarms.alarms.iter().filter(|alarm| may_modify(caller, alarm.owner));
+        Ok(owned.cloned().collect())
+    }
+
+    /// All users' alarms.
+    ///
+    /// This is readable by every user, use `owned_alarms` to get only the
+    /// alarms the caller may modify.
+    #[zbus(property)]
+    async fn alarms(&self) -> Vec<Alarm> {
+        let alarms = self.alarms.read().await;
+        alarms.alarms.clone()
+    }
+}
+
+/// Get the UID of a method call's sender.
+async fn caller_uid(connection: &Connection, header: &Header<'_>) -> Result<u32, DBusError> {
+    let sender = match header.sender() {
+        Some(sender) => sender,
+        None => return Err(DBusError::AccessDenied("Unknown sender".into())),
+    };
+
+    let dbus = DBusProxy::new(connection).await?;
+    Ok(dbus.get_connection_unix_user(BusName::from(sender.as_ref())).await?)
+}
+
+/// Validate client-provided alarm properties.
+fn validate(alarm: &Alarm, now: i64) -> Result<(), DBusError> {
+    if alarm.id.len() > MAX_ID_LENGTH {
+        let msg = format!("ID exceeds {MAX_ID_LENGTH} bytes");
+        return Err(DBusError::IdLength(msg));
+    }
+
+    if alarm.label.len() > MAX_LABEL_LENGTH {
+        let msg = format!("Alarm {:?} label exceeds {MAX_LABEL_LENGTH} bytes", alarm.id);
+        return Err(DBusError::LabelLength(msg));
+    }
+
+    if alarm.metadata.len() > MAX_METADATA_ENTRIES {
+        let msg =
+            format!("Alarm {:?} has more than {MAX_METADATA_ENTRIES} metadata entries", alarm.id);
+        return Err(DBusError::MetadataSize(msg));
+    }
+
+    let metadata_bytes: usize =
+        alarm.metadata.iter().map(|(key, value)| key.len() + value.len()).sum();
+    if metadata_bytes > MAX_METADATA_BYTES {
+        let msg = format!("Alarm {:?} metadata exceeds {MAX_METADATA_BYTES} bytes", alarm.id);
+        return Err(DBusError::MetadataSize(msg));
+    }
+
+    if alarm.ring_seconds > MAX_RING_SECONDS {
+        let msg = format!("Alarm {:?} rings longer than {MAX_RING_SECONDS} seconds", alarm.id);
+        return Err(DBusError::RingDuration(msg));
+    }
+
+    if alarm.ring_time() + alarm.ring_seconds as i64 <= now {
+        let msg = format!("Alarm {:?} has elapsed already", alarm.id);
+        return Err(DBusError::ElapsedTime(msg));
+    }
+
+    Ok(())
+}
+
+/// Check if a user may modify an alarm without additional authorization.
+fn may_modify(uid: u32, owner: u32) -> bool {
+    uid == 0 || uid == owner
+}
+
+/// Check if a method call's sender is authorized as administrator by polkit.
+async fn polkit_authorized(connection: &Connection, header: &Header<'_>) -> bool {
+    let sender = match header.sender() {
+        Some(sender) => sender,
+        None => return false,
+    };
+
+    let authority = match AuthorityProxy::new(connection).await {
+        Ok(authority) => authority,
+        Err(err) => {
+            error!("Could not connect to polkit: {err}");
+            return false;
+        },
+    };
+
+    let subject = ("system-bus-name", HashMap::from([("name", Value::from(sender.as_str()))]));
+    let flags = POLKIT_ALLOW_USER_INTERACTION;
+    match authority.check_authorization(&subject, POLKIT_ACTION, HashMap::new(), flags, "").await {
+        Ok((authorized, ..)) => authorized,
+        Err(err) => {
+            error!("Polkit authorization failed: {err}");
+            false
+        },
+    }
+}
+
+/// Reasons why an alarm cannot be snoozed.
+enum SnoozeError {
+    /// No ringing or dismissed alarm with this ID exists.
+    NotRinging,
+    /// Alarm reached the contained maximum number of snoozes.
+    Limit(u32),
+}
+
+/// Filesystem-based alarm store.
+struct Store {
+    alarms: Vec<Alarm>,
+    /// Dismissed alarm occurrences which can still be snoozed, until they are
+    /// acknowledged.
+    dismissed: Vec<Alarm>,
+    onchange_rx: watch::Receiver<()>,
+    onchange_tx: watch::Sender<()>,
+    clock: Arc<dyn Clock>,
+    db: Database,
+}
+
+impl Store {
+    fn new(db_path: impl AsRef<Path>, clock: Arc<dyn Clock>) -> Result<Self, IoError> {
+        // Parse existing alarms.
+        let db_path = db_path.as_ref();
+        let db = Database::new(db_path);
+        let mut alarms = db.load()?;
+
+        // Assign alarms stored before ownership was recorded to root.
+        for alarm in alarms.iter_mut().filter(|alarm| alarm.owner == UNOWNED) {
+            alarm.owner = 0;
+        }
+
+        // Create update channel.
+        let (onchange_tx, onchange_rx) = watch::channel(());
+
+        debug!("Alarms in DB {db_path:?}: {alarms:?}");
+
+        Ok(Self { db, alarms, onchange_rx, onchange_tx, clock, dismissed: Default::default() })
+    }
+
+    /// Subscribe to changes.
+    fn subscribe(&self) -> watch::Receiver<()> {
+        self.onchange_rx.clone()
+    }
+
+    /// Get the next enabled alarm.
+    fn upcoming(&self) -> Option<&Alarm> {
+        self.alarms.iter().filter(|alarm| alarm.enabled).min_by_key(|alarm| alarm.ring_time())
+    }
+
+    /// Add a new alarm.
+    ///
+    /// Returns `true` if the alarm was added and `false` if another alarm with
+    /// the same ID already exists.
+    fn add(&mut self, alarm: Alarm) -> bool {
+        if self.alarms.iter().any(|existing_alarm| existing_alarm.id == alarm.id) {
+            return false;
+        }
+
+        // Ensure snoozing cannot resurrect an unrelated alarm with the same ID.
+        self.dismissed.retain(|dismissed| dismissed.id != alarm.id);
+
+        self.alarms.push(alarm);
+
+        self.sync();
+
+        true
+    }
+
+    /// Replace an existing alarm.
+    ///
+    /// The existing alarm's owner is kept.
+    ///
+    /// Returns the previous alarm, or `None` if no alarm with the new alarm's
+    /// ID exists.
+    fn update(&mut self, mut alarm: Alarm) -> Option<Alarm> {
+        let existing = self.alarms.iter_mut().find(|existing| existing.id == alarm.id)?;
+        alarm.owner = existing.owner;
+        let previous = mem::replace(existing, alarm);
+
+        // Ensure snoozing cannot resurrect the alarm's previous schedule.
+        self.dismissed.retain(|dismissed| dismissed.id != previous.id);
+
+        self.sync();
+
+        Some(previous)
+    }
+
+    /// Number of alarms owned by a user.
+    fn owned_count(&self, owner: u32) -> usize {
+        self.alarms.iter().filter(|alarm| alarm.owner == owner).count()
+    }
+
+    /// Remove an existing alarm.
+    fn remove(&mut self, id: &str) -> Option<Alarm> {
+        let matching = self.alarms.iter().position(|alarm| alarm.id == id)?;
+        let removed = self.alarms.remove(matching);
+        self.dismissed.retain(|alarm| alarm.id != id);
+
+        self.sync();
+
+        Some(removed)
+    }
+
+    /// Enable or disable an alarm.
+    ///
+    /// Enabled alarms whose time has passed while they were disabled are moved
+    /// to their next occurrence, or the next day for one-time alarms.
+    ///
+    /// Returns the alarm's state before it was changed.
+    fn set_enabled(&mut self, id: &str, enabled: bool) -> Option<Alarm> {
+        let alarm = self.alarms.iter_mut().find(|alarm| alarm.id == id)?;
+        let previous = alarm.clone();
+
+        // Ignore alarms which are already in the requested state.
+        if alarm.enabled == enabled {
+            return Some(previous);
+        }
+        alarm.enabled = enabled;
+
+        let now = self.clock.unix_now();
+        if !enabled {
+            // Drop snoozes, so re-enabling restores the regular schedule.
+            alarm.snoozed_until = 0;
+            alarm.snoozes = 0;
+            self.dismissed.retain(|dismissed| dismissed.id != id);
+        } else if alarm.unix_time <= now {
+            let next = alarm.next_occurrence(now, &*self.clock).or_else(|| {
+                let mut daily = alarm.clone();
+                daily.recurrence = Recurrence::Interval(1);
+                daily.next_occurrence(now, &*self.clock)
+            });
+            if let Some(unix_time) = next {
+                alarm.unix_time = unix_time;
+            }
+        }
+
+        self.sync();
+
+        Some(previous)
+    }
+
+    /// Finish the current occurrence of an alarm.
+    ///
+    /// Returns the alarm's state before it was dismissed.
+    fn dismiss(&mut self, id: &str) -> Option<Alarm> {
+        let index = self.alarms.iter().position(|alarm| alarm.id == id)?;
+        let dismissed = self.alarms[index].clone();
+
+        // Reschedule recurring alarms, or remove them if there's no next occurrence.
+        if !Self::finish_occurrence(&mut self.alarms[index], &*self.clock) {
+            self.alarms.remove(index);
+        }
+
+        // Keep the dismissed occurrence around to allow snoozing it.
+        self.dismissed.retain(|alarm| alarm.id != id);
+        self.dismissed.push(dismissed.clone());
+
+        self.sync();
+
+        Some(dismissed)
+    }
+
+    /// Snooze a ringing alarm.
+    ///
+    /// Fails if there is no ringing or dismissed alarm with this ID, or if the
+    /// alarm was snoozed `max_snoozes` times already.
+    fn snooze(&mut self, id: &str, minutes: u32, max_snoozes: u32) -> Result<(), SnoozeError> {
+        let now = self.clock.unix_now();
+
+        // Dismissed occurrences take precedence, since recurring alarms will already
+        // have moved on to their next occurrence.
+        let dismissed = self.dismissed.iter().position(|alarm| alarm.id == id);
+        let index = self.alarms.iter().position(|alarm| alarm.id == id);
+
+        let snoozes = match (dismissed, index) {
+            (Some(dismissed), _) => self.dismissed[dismissed].snoozes,
+            (None, Some(index))
+                if self.alarms[index].enabled && self.alarms[index].ring_time() <= now =>
+            {
+                self.alarms[index].snoozes
+            },
+            (None, _) => return Err(SnoozeError::NotRinging),
+        };
+
+        if snoozes >= max_snoozes {
+            return Err(SnoozeError::Limit(max_snoozes));
+        }
+
+        let dismissed = dismissed.map(|index| self.dismissed.swap_remove(index));
+
+        // Re-add dismissed alarms without further occurrences.
+        let alarm = match index {
+            Some(index) => &mut self.alarms[index],
+            None => {
+                self.alarms.extend(dismissed);
+                self.alarms.last_mut().unwrap()
+            },
+        };
+        alarm.snoozed_until = now + minutes as i64 * 60;
+        alarm.snoozes = snoozes + 1;
+
+        self.sync();
+
+        Ok(())
+    }
+
+    /// Stop tracking a dismissed alarm occurrence.
+    ///
+    /// Returns `false` if there is no dismissed occurrence with this ID.
+    fn acknowledge(&mut self, id: &str) -> bool {
+        let count = self.dismissed.len();
+        self.dismissed.retain(|alarm| alarm.id != id);
+        self.dismissed.len() != count
+    }
+
+    /// Remove or reschedule all elapsed alarms.
+    ///
+    /// Recurring alarms are moved to their next occurrence instead of being
+    /// removed.
+    ///
+    /// Returns the number of elapsed alarms.
+    fn remove_elapsed(&mut self) -> usize {
+        let now = self.clock.unix_now();
+        let mut elapsed_count = 0;
+
+        // Forget unacknowledged occurrences, in case their client went away.
+        self.dismissed.retain(|alarm| {
+            alarm.ring_time() + alarm.ring_seconds as i64 + ACKNOWLEDGE_TIMEOUT > now
+        });
+
+        self.alarms.retain_mut(|alarm| {
+            // Keep disabled alarms untouched until they are enabled again.
+            if !alarm.enabled || alarm.ring_time() + alarm.ring_seconds as i64 > now {
+                return true;
+            }
+            elapsed_count += 1;
+
+            Self::finish_occurrence(alarm, &*self.clock)
+        });
+
+        // Update database if entries were changed.
+        if elapsed_count > 0 {
+            self.sync();
+        }
+
+        elapsed_count
+    }
+
+    /// Move an alarm past its current occurrence.
+    ///
+    /// Snoozed alarms return to their regular schedule, while recurring alarms
+    /// advance to their next occurrence.
+    ///
+    /// Returns `false` if the alarm has no further occurrences.
+    fn finish_occurrence(alarm: &mut Alarm, clock: &dyn Clock) -> bool {
+        let now = clock.unix_now();
+        alarm.snoozes = 0;
+
+        // Keep snoozed alarms whose regular occurrence hasn't started yet.
+        if mem::take(&mut alarm.snoozed_until) != 0 && alarm.unix_time > now {
+            return true;
+        }
+
+        match alarm.next_occurrence(alarm.unix_time.max(now), clock) {
+            Some(unix_time) => {
+                alarm.unix_time = unix_time;
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Write all pending DB changes to the filesystem and signal changes.
+    fn sync(&mut self) {
+        // Signal changes.
+        let _ = self.onchange_tx.send(());
+
+        // Atomically replace the entire file.
+        if let Err(err) = self.db.write(&self.alarms) {
+            error!("Failed DB sync: {err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use time::macros::{datetime, offset};
+
+    use super::*;
+    use crate::clock::FakeClock;
+    use crate::testing;
+
+    /// Create an alarm store in a new temporary directory.
+    fn test_store(name: &str, clock: Arc<FakeClock>) -> Store {
+        Store::new(testing::temp_dir(&format!("store-{name}")).join("alarms.db"), clock).unwrap()
+    }
+
+    /// Create an alarm ringing for a minute at `time`.
+    fn alarm(id: &str, time: OffsetDateTime, recurrence: &str) -> Alarm {
+        let mut alarm = Alarm::new(id, time.unix_timestamp(), 60);
+        alarm.recurrence = Recurrence::from_str(recurrence).unwrap();
+        alarm
+    }
+
+    #[test]
+    fn midnight_rollover() {
+        let clock = Arc::new(FakeClock::new(datetime!(2025-10-19 21:00 UTC)));
+        clock.set_offset(OffsetDateTime::UNIX_EPOCH, offset!(+2));
+        let mut store = test_store("midnight", clock.clone());
+
+        // Monday 00:15 local time is still Sunday in UTC.
+        let monday = datetime!(2025-10-20 00:15 +2);
+        store.add(alarm("weekly", monday, "weekly:mon"));
+
+        clock.set(monday + Duration::minutes(5));
+        assert_eq!(store.remove_elapsed(), 1);
+        assert_eq!(store.alarms[0].unix_time, datetime!(2025-10-27 00:15 +2).unix_timestamp());
+    }
+
+    #[test]
+    fn dst_transition() {
+        let clock = Arc::new(FakeClock::new(datetime!(2025-03-29 06:00 +1)));
+        clock.set_offset(OffsetDateTime::UNIX_EPOCH, offset!(+1));
+        clock.set_offset(datetime!(2025-03-30 01:00 UTC), offset!(+2));
+        let mut store = test_store("dst", clock.clone());
+
+        // Daily alarms keep their local time after the switch to DST.
+        store.add(alarm("daily", datetime!(2025-03-29 07:00 +1), "interval:1"));
+        clock.set(datetime!(2025-03-29 07:30 +1));
+        assert_eq!(store.remove_elapsed(), 1);
+        assert_eq!(store.alarms[0].unix_time, datetime!(2025-03-30 07:00 +2).unix_timestamp());
+
+        // Alarms in the skipped hour ring right after it.
+        store.add(alarm("skipped", datetime!(2025-03-29 02:30 +1), "interval:1"));
+        clock.set(datetime!(2025-03-29 02:40 +1));
+        store.remove_elapsed();
+        let skipped = store.alarms.iter().find(|alarm| alarm.id == "skipped").unwrap();
+        assert_eq!(skipped.unix_time, datetime!(2025-03-30 03:30 +2).unix_timestamp());
+
+        // One-time alarms in the repeated hour keep their exact time.
+        clock.set_offset(datetime!(2025-10-26 01:00 UTC), offset!(+1));
+        let ambiguous = alarm("ambiguous", datetime!(2025-10-26 02:30 +2), "once");
+        let unix_time = ambiguous.unix_time;
+        assert_eq!(ambiguous.next_occurrence(unix_time - 1, &*clock), Some(unix_time));
+        assert_eq!(ambiguous.next_occurrence(unix_time, &*clock), None);
+    }
+
+    #[test]
+    fn elapse_during_suspend() {
+        let clock = Arc::new(FakeClock::new(datetime!(2025-10-17 22:00 UTC)));
+        let mut store = test_store("suspend", clock.clone());
+
+        store.add(alarm("once", datetime!(2025-10-18 06:00 UTC), "once"));
+        store.add(alarm("daily", datetime!(2025-10-18 07:00 UTC), "interval:1"));
+
+        // Resume from suspend long after both alarms have finished ringing.
+        clock.set(datetime!(2025-10-20 12:00 UTC));
+        assert_eq!(store.remove_elapsed(), 2);
+
+        // Missed occurrences are skipped, instead of ringing after resume.
+        let daily = datetime!(2025-10-21 07:00 UTC).unix_timestamp();
+        assert_eq!(store.alarms.len(), 1);
+        assert_eq!(store.alarms[0].unix_time, daily);
+        assert_eq!(store.upcoming().map(|alarm| alarm.ring_time()), Some(daily));
+    }
+
+    #[test]
+    fn update_keeps_id() {
+        let clock = Arc::new(FakeClock::new(datetime!(2025-10-17 22:00 UTC)));
+        let mut store = test_store("update", clock.clone());
+
+        store.add(alarm("a", datetime!(2025-10-18 06:00 UTC), "once"));
+        store.add(alarm("b", datetime!(2025-10-18 07:00 UTC), "once"));
+
+        let previous = store.update(alarm("a", datetime!(2025-10-18 08:00 UTC), "once"));
+        let unix_time = datetime!(2025-10-18 06:00 UTC).unix_timestamp();
+        assert_eq!(previous.map(|alarm| alarm.unix_time), Some(unix_time));
+        assert_eq!(store.alarms.len(), 2);
+        assert_eq!(store.upcoming().map(|alarm| alarm.id.as_str()), Some("b"));
+
+        // Updating unknown alarms must not add them.
+        assert_eq!(store.update(alarm("c", datetime!(2025-10-18 09:00 UTC), "once")), None);
+        assert_eq!(store.alarms.len(), 2);
+    }
+
+    #[test]
+    fn disabled_alarms() {
+        let clock = Arc::new(FakeClock::new(datetime!(2025-10-17 22:00 UTC)));
+        let mut store = test_store("disabled", clock.clone());
+
+        store.add(alarm("once", datetime!(2025-10-18 06:00 UTC), "once"));
+        store.add(alarm("daily", datetime!(2025-10-18 07:00 UTC), "interval:1"));
+        store.set_enabled("once", false);
+        store.set_enabled("daily", false);
+        assert_eq!(store.upcoming(), None);
+
+        // Disabled alarms are kept after their time has passed.
+        clock.set(datetime!(2025-10-18 12:00 UTC));
+        assert_eq!(store.remove_elapsed(), 0);
+        assert_eq!(store.alarms.len(), 2);
+
+        // Re-enabled alarms move to their next occurrence.
+        store.set_enabled("once", true);
+        store.set_enabled("daily", true);
+        let once = datetime!(2025-10-19 06:00 UTC).unix_timestamp();
+        let daily = datetime!(2025-10-19 07:00 UTC).unix_timestamp();
+        assert_eq!(store.alarms[0].unix_time, once);
+        assert_eq!(store.alarms[1].unix_time, daily);
+        assert_eq!(store.upcoming().map(|alarm| alarm.ring_time()), Some(once));
+    }
+
+    #[test]
+    fn snooze_limit() {
+        let clock = Arc::new(FakeClock::new(datetime!(2025-10-18 06:00 UTC)));
+        let mut store = test_store("snooze-limit", clock.clone());
+
+        store.add(alarm("a", datetime!(2025-10-18 06:00 UTC), "once"));
+        assert!(matches!(store.snooze("b", 5, 2), Err(SnoozeError::NotRinging)));
+
+        assert!(store.snooze("a", 5, 2).is_ok());
+        clock.set(datetime!(2025-10-18 06:05 UTC));
+        assert!(store.snooze("a", 5, 2).is_ok());
+        clock.set(datetime!(2025-10-18 06:10 UTC));
+        assert!(matches!(store.snooze("a", 5, 2), Err(SnoozeError::Limit(2))));
+        assert_eq!(store.alarms[0].snoozes, 2);
+    }
+
+    #[test]
+    fn snooze_dismissed() {
+        let clock = Arc::new(FakeClock::new(datetime!(2025-10-18 06:00 UTC)));
+        let mut store = test_store("snooze-dismissed", clock.clone());
+
+        // Disabled alarms are not ringing.
+        store.add(alarm("a", datetime!(2025-10-18 06:00 UTC), "interval:1"));
+        store.set_enabled("a", false);
+        assert!(matches!(store.snooze("a", 5, 3), Err(SnoozeError::NotRinging)));
+        store.set_enabled("a", true);
+
+        // Dismissed occurrences can be snoozed after they stopped ringing.
+        clock.set(datetime!(2025-10-18 07:00 UTC));
+        store.add(alarm("b", datetime!(2025-10-18 07:00 UTC), "once"));
+        assert!(store.dismiss("b").is_some());
+        clock.set(datetime!(2025-10-18 08:00 UTC));
+        store.remove_elapsed();
+        assert!(store.snooze("b", 5, 3).is_ok());
+
+        // Acknowledged occurrences cannot be snoozed anymore.
+        clock.set(datetime!(2025-10-18 08:05 UTC));
+        assert!(store.dismiss("b").is_some());
+        assert!(store.acknowledge("b"));
+        assert!(!store.acknowledge("b"));
+        assert!(matches!(store.snooze("b", 5, 3), Err(SnoozeError::NotRinging)));
+    }
+
+    #[test]
+    fn ownership() {
+        // Owners and root can modify alarms.
+        assert!(may_modify(1000, 1000));
+        assert!(may_modify(0, 1000));
+        assert!(!may_modify(1001, 1000));
+
+        // Alarms without owner cannot be modified by other users.
+        assert!(!may_modify(1001, UNOWNED));
+    }
+
+    #[test]
+    fn unowned_migration() {
+        let clock = Arc::new(FakeClock::new(datetime!(2025-10-18 06:00 UTC)));
+        let db_path = testing::temp_dir("store-unowned").join("alarms.db");
+        let mut store = Store::new(&db_path, clock.clone()).unwrap();
+        store.add(alarm("a", datetime!(2025-10-18 07:00 UTC), "once"));
+        assert_eq!(store.alarms[0].owner, UNOWNED);
+
+        // Alarms without owner are assigned to root when loaded.
+        let store = Store::new(&db_path, clock).unwrap();
+        assert_eq!(store.alarms[0].owner, 0);
+    }
+
+    #[test]
+    fn validation() {
+        let valid = Alarm::new("a", 100, 60);
+        assert!(validate(&valid, 100).is_ok());
+
+        // Alarms which are still ringing are accepted.
+        assert!(validate(&valid, 159).is_ok());
+        let elapsed = validate(&valid, 160);
+        assert!(matches!(elapsed, Err(DBusError::ElapsedTime(_))), "{elapsed:?}");
+
+        let long_ringing = Alarm::new("a", 100, MAX_RING_SECONDS + 1);
+        let ring_duration = validate(&long_ringing, 0);
+        assert!(matches!(ring_duration, Err(DBusError::RingDuration(_))), "{ring_duration:?}");
+
+        let long_id = Alarm::new("a".repeat(MAX_ID_LENGTH + 1), 100, 60);
+        let id_length = validate(&long_id, 0);
+        assert!(matches!(id_length, Err(DBusError::IdLength(_))), "{id_length:?}");
+
+        let mut long_label = Alarm::new("a", 100, 60);
+        long_label.label = "a".repeat(MAX_LABEL_LENGTH + 1);
+        let label_length = validate(&long_label, 0);
+        assert!(matches!(label_length, Err(DBusError::LabelLength(_))), "{label_length:?}");
+
+        let mut many_entries = Alarm::new("a", 100, 60);
+        for i in 0..=MAX_METADATA_ENTRIES {
+            many_entries.metadata.insert(i.to_string(), String::new());
+        }
+        let metadata_size = validate(&many_entries, 0);
+        assert!(matches!(metadata_size, Err(DBusError::MetadataSize(_))), "{metadata_size:?}");
+
+        let mut large_metadata = Alarm::new("a", 100, 60);
+        large_metadata.metadata.insert("a".into(), "a".repeat(MAX_METADATA_BYTES));
+        let metadata_size = validate(&large_metadata, 0);
+        assert!(matches!(metadata_size, Err(DBusError::MetadataSize(_))), "{metadata_size:?}");
+    }
+}
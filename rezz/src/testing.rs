@@ -0,0 +1,150 @@
+//! Test helpers for rezz and its clients.
+//!
+//! This is only available with the `testing` feature.
+
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::Arc;
+use std::{env, fs, process};
+
+use nix::unistd;
+use zbus::Connection;
+use zbus::connection::Builder;
+
+use crate::Alarm;
+use crate::clock::Clock;
+use crate::server::{self, Server};
+use crate::wakeup::Mock;
+
+/// Create a new empty temporary directory.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("rezz-{}-{name}", process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Create an alarm owned by the current user.
+pub fn owned_alarm(id: &str, unix_time: i64, ring_seconds: u32) -> Alarm {
+    let mut alarm = Alarm::new(id, unix_time, ring_seconds);
+    alarm.owner = unistd::getuid().as_raw();
+    alarm
+}
+
+/// Private DBus message bus.
+///
+/// Tests using the bus should be skipped if no `dbus-daemon` binary is
+/// available, unless the `CI` environment variable is set.
+pub struct TestBus {
+    pub address: String,
+    daemon: Child,
+    dir: PathBuf,
+}
+
+impl TestBus {
+    /// Start a new message bus.
+    ///
+    /// Returns `None` if `dbus-daemon` could not be started.
+    pub fn new(name: &str) -> Option<Self> {
+        let dir = temp_dir(&format!("bus-{name}"));
+
+        let config_path = dir.join("bus.conf");
+        let config = format!(
+            r#"<busconfig>
+                <type>session</type>
+                <listen>unix:path={}</listen>
+                <auth>EXTERNAL</auth>
+                <policy context="default">
+                    <allow send_destination="*" eavesdrop="true"/>
+                    <allow eavesdrop="true"/>
+                    <allow own="*"/>
+                </policy>
+            </busconfig>"#,
+            dir.join("bus").display(),
+        );
+        fs::write(&config_path, config).unwrap();
+
+        let (daemon, address) = Self::spawn_daemon(&config_path)?;
+
+        Some(Self { address, daemon, dir })
+    }
+
+    /// Restart the message bus on the same address.
+    pub fn restart(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+        let _ = fs::remove_file(self.dir.join("bus"));
+
+        let (daemon, address) = Self::spawn_daemon(&self.dir.join("bus.conf")).unwrap();
+        assert_eq!(address, self.address);
+        self.daemon = daemon;
+    }
+
+    /// Connect to the message bus.
+    pub async fn connect(&self) -> Connection {
+        Builder::address(self.address.as_str()).unwrap().build().await.unwrap()
+    }
+
+    /// Start a rezz server on this bus, returning its wakeup backend.
+    pub async fn serve(&self, clock: Arc<dyn Clock>) -> Arc<Mock> {
+        // The server keeps its own handle to the connection.
+        let (_, wakeup) = self.serve_connection(clock).await;
+        wakeup
+    }
+
+    /// Start a rezz server on this bus.
+    ///
+    /// Returns the server's DBus connection and wakeup backend.
+    pub async fn serve_connection(&self, clock: Arc<dyn Clock>) -> (Connection, Arc<Mock>) {
+        let wakeup = Arc::new(Mock::default());
+        let connection = self.connect().await;
+        let server =
+            Server::new(connection.clone(), self.db_path(), wakeup.clone(), clock).await.unwrap();
+        connection.request_name(server::NAME).await.unwrap();
+        tokio::spawn(server.run());
+        (connection, wakeup)
+    }
+
+    /// Alarm database path.
+    pub fn db_path(&self) -> PathBuf {
+        self.dir.join("alarms.db")
+    }
+
+    /// Start a `dbus-daemon` process and wait for its address.
+    fn spawn_daemon(config_path: &Path) -> Option<(Child, String)> {
+        let mut daemon = Command::new("dbus-daemon")
+            .arg("--nofork")
+            .arg("--print-address")
+            .arg(format!("--config-file={}", config_path.display()))
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .inspect_err(|err| {
+                // Never silently skip the integration tests in CI.
+                if env::var_os("CI").is_some() {
+                    panic!("Could not start dbus-daemon: {err}");
+                }
+                eprintln!("Skipping test, could not start dbus-daemon: {err}");
+            })
+            .ok()?;
+
+        // Wait for the bus to be ready.
+        let mut address = String::new();
+        let stdout = daemon.stdout.take().unwrap();
+        BufReader::new(stdout).read_line(&mut address).unwrap();
+
+        // Strip the server GUID, since it changes when the bus is restarted.
+        let address = address.trim().split(",guid=").next().unwrap().into();
+
+        Some((daemon, address))
+    }
+}
+
+impl Drop for TestBus {
+    fn drop(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
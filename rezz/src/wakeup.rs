@@ -0,0 +1,272 @@
+//! System wakeup scheduling backends.
+
+use std::fs::{self, File};
+use std::io::{Error as IoError, ErrorKind as IoErrorKind};
+use std::mem::MaybeUninit;
+use std::os::fd::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use nix::sys::time::TimeSpec;
+use nix::sys::timerfd::{
+    ClockId, Expiration, TimerFd as NixTimerFd, TimerFlags, TimerSetTimeFlags,
+};
+use time::OffsetDateTime;
+
+use crate::Error;
+use crate::ioctl::{self, RtcWkalm};
+
+/// Primary RTC path, should always exist for systems with RTC.
+pub const RTC_PATH: &str = "/dev/rtc";
+
+/// Scheduler for system wakeups.
+///
+/// Every backend manages a single wakeup time, scheduling a new wakeup will
+/// replace the existing one.
+pub trait WakeupBackend: Send + Sync {
+    /// Set a new wakeup time.
+    fn set(&self, time: OffsetDateTime) -> Result<(), Error>;
+
+    /// Get the currently scheduled wakeup time.
+    fn get(&self) -> Result<Option<OffsetDateTime>, Error>;
+
+    /// Clear all current wakeup times.
+    fn clear(&self) -> Result<(), Error>;
+}
+
+/// RTC wakeups through `/dev/rtcN` ioctls.
+pub struct Rtc {
+    path: PathBuf,
+}
+
+impl Rtc {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl WakeupBackend for Rtc {
+    fn set(&self, time: OffsetDateTime) -> Result<(), Error> {
+        let rtc_file = File::open(&self.path)?;
+        unsafe { ioctl::rtc_wkalm_set(rtc_file.as_raw_fd(), &time.into() as *const _)? };
+        Ok(())
+    }
+
+    fn get(&self) -> Result<Option<OffsetDateTime>, Error> {
+        let rtc_file = File::open(&self.path)?;
+        let mut time: MaybeUninit<RtcWkalm> = MaybeUninit::uninit();
+        let time = unsafe {
+            ioctl::rtc_wkalm_rd(rtc_file.as_raw_fd(), time.as_mut_ptr())?;
+            time.assume_init()
+        };
+        Ok(time.into())
+    }
+
+    fn clear(&self) -> Result<(), Error> {
+        let rtc_file = File::open(&self.path)?;
+        unsafe { ioctl::rtc_wkalm_set(rtc_file.as_raw_fd(), &ioctl::RESET_ALARM as *const _)? };
+        Ok(())
+    }
+}
+
+/// Wakeups using a `CLOCK_REALTIME_ALARM` timerfd.
+///
+/// The wakeup is only active while this backend is alive.
+pub struct TimerFd {
+    timer: NixTimerFd,
+    /// Absolute time the timer was armed for.
+    armed: Mutex<Option<OffsetDateTime>>,
+}
+
+impl TimerFd {
+    pub fn new() -> Result<Self, Error> {
+        let timer = NixTimerFd::new(ClockId::CLOCK_REALTIME_ALARM, TimerFlags::TFD_CLOEXEC)?;
+        Ok(Self { timer, armed: Default::default() })
+    }
+}
+
+impl WakeupBackend for TimerFd {
+    fn set(&self, time: OffsetDateTime) -> Result<(), Error> {
+        let nanos = time.unix_timestamp_nanos();
+        let timespec = TimeSpec::new((nanos / 1_000_000_000) as _, (nanos % 1_000_000_000) as _);
+        self.timer.set(Expiration::OneShot(timespec), TimerSetTimeFlags::TFD_TIMER_ABSTIME)?;
+        *self.armed.lock().unwrap() = Some(time);
+        Ok(())
+    }
+
+    fn get(&self) -> Result<Option<OffsetDateTime>, Error> {
+        // Timerfd only reports the remaining time, which cannot be converted back
+        // to the exact wakeup time, so report the armed time while it's pending.
+        match self.timer.get()? {
+            Some(_) => Ok(*self.armed.lock().unwrap()),
+            None => Ok(None),
+        }
+    }
+
+    fn clear(&self) -> Result<(), Error> {
+        self.timer.unset()?;
+        *self.armed.lock().unwrap() = None;
+        Ok(())
+    }
+}
+
+/// RTC wakeups through `/sys/class/rtc/rtcN/wakealarm`.
+pub struct Sysfs {
+    path: PathBuf,
+}
+
+impl Sysfs {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Get the sysfs wakealarm backend for an RTC device.
+    pub fn for_rtc(rtc: impl AsRef<Path>) -> Result<Self, Error> {
+        // Resolve symlinks like `/dev/rtc` to get the RTC's name.
+        let rtc = fs::canonicalize(rtc)?;
+        let name = rtc.file_name().ok_or_else(|| {
+            let msg = format!("Invalid RTC path: {rtc:?}");
+            IoError::new(IoErrorKind::InvalidInput, msg)
+        })?;
+
+        Ok(Self::new(Path::new("/sys/class/rtc").join(name).join("wakealarm")))
+    }
+}
+
+impl WakeupBackend for Sysfs {
+    fn set(&self, time: OffsetDateTime) -> Result<(), Error> {
+        // Existing alarms must be cleared before a new one can be set.
+        self.clear()?;
+
+        fs::write(&self.path, time.unix_timestamp().to_string())?;
+        Ok(())
+    }
+
+    fn get(&self) -> Result<Option<OffsetDateTime>, Error> {
+        let content = fs::read_to_string(&self.path)?;
+        let content = content.trim();
+
+        // Sysfs reports an empty file without any wakealarm set.
+        if content.is_empty() {
+            return Ok(None);
+        }
+
+        let unix_time = content.parse::<i64>().map_err(|err| {
+            let msg = format!("Invalid wakealarm {content:?}: {err}");
+            IoError::new(IoErrorKind::InvalidData, msg)
+        })?;
+        let time = OffsetDateTime::from_unix_timestamp(unix_time).map_err(|err| {
+            let msg = format!("Invalid wakealarm {content:?}: {err}");
+            IoError::new(IoErrorKind::InvalidData, msg)
+        })?;
+
+        Ok(Some(time))
+    }
+
+    fn clear(&self) -> Result<(), Error> {
+        fs::write(&self.path, "0")?;
+        Ok(())
+    }
+}
+
+/// In-memory wakeup backend.
+///
+/// This does not wake up the system, but records all scheduled wakeups.
+#[derive(Default)]
+pub struct Mock {
+    state: Mutex<MockState>,
+}
+
+impl Mock {
+    /// Get all wakeup times which have been set, in order of scheduling.
+    pub fn history(&self) -> Vec<OffsetDateTime> {
+        self.state.lock().unwrap().history.clone()
+    }
+}
+
+impl WakeupBackend for Mock {
+    fn set(&self, time: OffsetDateTime) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        state.history.push(time);
+        state.wakeup = Some(time);
+        Ok(())
+    }
+
+    fn get(&self) -> Result<Option<OffsetDateTime>, Error> {
+        Ok(self.state.lock().unwrap().wakeup)
+    }
+
+    fn clear(&self) -> Result<(), Error> {
+        self.state.lock().unwrap().wakeup = None;
+        Ok(())
+    }
+}
+
+/// Mock backend state.
+#[derive(Default)]
+struct MockState {
+    history: Vec<OffsetDateTime>,
+    wakeup: Option<OffsetDateTime>,
+}
+
+#[cfg(test)]
+mod tests {
+    use time::Duration;
+
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn sysfs() {
+        let path = testing::temp_dir("sysfs").join("wakealarm");
+        fs::write(&path, "").unwrap();
+        let sysfs = Sysfs::new(&path);
+        assert_eq!(sysfs.get().unwrap(), None);
+
+        let time = OffsetDateTime::from_unix_timestamp(1_760_000_000).unwrap();
+        sysfs.set(time).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "1760000000");
+        assert_eq!(sysfs.get().unwrap(), Some(time));
+
+        sysfs.clear().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "0");
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn timerfd() {
+        // Alarm timers require `CAP_WAKE_ALARM`.
+        let timerfd = match TimerFd::new() {
+            Ok(timerfd) => timerfd,
+            Err(err) => {
+                eprintln!("Skipping test, could not create alarm timer: {err}");
+                return;
+            },
+        };
+        assert_eq!(timerfd.get().unwrap(), None);
+
+        // The exact wakeup time is reported, to allow unscheduling it.
+        let time = OffsetDateTime::now_utc().replace_nanosecond(0).unwrap() + Duration::hours(1);
+        timerfd.set(time).unwrap();
+        assert_eq!(timerfd.get().unwrap(), Some(time));
+
+        timerfd.clear().unwrap();
+        assert_eq!(timerfd.get().unwrap(), None);
+    }
+
+    #[test]
+    fn mock() {
+        let mock = Mock::default();
+        let first = OffsetDateTime::from_unix_timestamp(100).unwrap();
+        let second = OffsetDateTime::from_unix_timestamp(200).unwrap();
+
+        mock.set(first).unwrap();
+        mock.set(second).unwrap();
+        assert_eq!(mock.get().unwrap(), Some(second));
+
+        mock.clear().unwrap();
+        assert_eq!(mock.get().unwrap(), None);
+        assert_eq!(mock.history(), vec![first, second]);
+    }
+}
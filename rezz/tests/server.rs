@@ -0,0 +1,327 @@
+//! DBus server integration tests.
+
+use std::fs;
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+
+use futures_util::stream::StreamExt;
+use rezz::clock::FakeClock;
+use rezz::testing::{TestBus, owned_alarm};
+use rezz::wakeup::WakeupBackend;
+use rezz::{Alarm, server};
+use time::{Duration, OffsetDateTime};
+use tokio::time as tokio_time;
+use zbus::proxy;
+
+/// Start time of the fake clock.
+const START: i64 = 1_760_000_000;
+
+#[proxy(
+    interface = "org.catacombing.rezz",
+    default_service = "org.catacombing.rezz",
+    default_path = "/org/catacombing/rezz"
+)]
+trait Rezz {
+    async fn add_alarm(&self, alarm: Alarm) -> zbus::Result<()>;
+
+    async fn update_alarm(&self, alarm: Alarm) -> zbus::Result<()>;
+
+    async fn remove_alarm(&self, id: String) -> zbus::Result<()>;
+
+    async fn set_alarm_enabled(&self, id: String, enabled: bool) -> zbus::Result<()>;
+
+    async fn snooze_alarm(&self, id: String, minutes: u32) -> zbus::Result<()>;
+
+    async fn acknowledge_alarm(&self, id: String) -> zbus::Result<()>;
+
+    async fn owned_alarms(&self) -> zbus::Result<Vec<Alarm>>;
+
+    #[zbus(property)]
+    fn alarms(&self) -> zbus::Result<Vec<Alarm>>;
+}
+
+/// Create a fake clock at the test start time.
+fn fake_clock() -> Arc<FakeClock> {
+    Arc::new(FakeClock::new(OffsetDateTime::from_unix_timestamp(START).unwrap()))
+}
+
+/// Wait for the next alarms property update.
+async fn next_alarms(stream: &mut zbus::proxy::PropertyStream<'_, Vec<Alarm>>) -> Vec<Alarm> {
+    let changed = tokio_time::timeout(StdDuration::from_secs(5), stream.next()).await;
+    changed.expect("alarms change timeout").unwrap().get().await.unwrap()
+}
+
+#[tokio::test]
+async fn add_remove() {
+    let Some(bus) = TestBus::new("add-remove") else { return };
+    let wakeup = bus.serve(fake_clock()).await;
+
+    let connection = bus.connect().await;
+    let rezz = RezzProxy::new(&connection).await.unwrap();
+
+    let alarm = owned_alarm("a", START + 60, 30);
+    rezz.add_alarm(alarm.clone()).await.unwrap();
+    assert_eq!(rezz.alarms().await.unwrap(), vec![alarm.clone()]);
+
+    let wakeup_time = OffsetDateTime::from_unix_timestamp(alarm.unix_time).unwrap();
+    assert_eq!(wakeup.get().unwrap(), Some(wakeup_time));
+
+    rezz.remove_alarm(alarm.id.clone()).await.unwrap();
+    assert_eq!(rezz.alarms().await.unwrap(), Vec::new());
+    assert_eq!(wakeup.get().unwrap(), None);
+
+    // Alarms are owned by the user which added them.
+    rezz.add_alarm(Alarm::new("b", START + 60, 30)).await.unwrap();
+    assert_eq!(rezz.owned_alarms().await.unwrap(), vec![owned_alarm("b", START + 60, 30)]);
+    rezz.remove_alarm("b".into()).await.unwrap();
+
+    // Removing the alarm a second time must fail.
+    assert!(rezz.remove_alarm(alarm.id).await.is_err());
+}
+
+#[tokio::test]
+async fn update() {
+    let Some(bus) = TestBus::new("update") else { return };
+    let wakeup = bus.serve(fake_clock()).await;
+
+    let connection = bus.connect().await;
+    let rezz = RezzProxy::new(&connection).await.unwrap();
+
+    let alarm = owned_alarm("a", START + 60, 30);
+    rezz.add_alarm(alarm).await.unwrap();
+
+    // Delaying the alarm re-arms the wakeup.
+    let later = owned_alarm("a", START + 120, 30);
+    rezz.update_alarm(later.clone()).await.unwrap();
+    assert_eq!(rezz.alarms().await.unwrap(), vec![later.clone()]);
+
+    let wakeup_time = OffsetDateTime::from_unix_timestamp(later.unix_time).unwrap();
+    assert_eq!(wakeup.get().unwrap(), Some(wakeup_time));
+
+    // Updating an unknown alarm must fail.
+    assert!(rezz.update_alarm(owned_alarm("b", START + 60, 30)).await.is_err());
+    assert_eq!(rezz.alarms().await.unwrap(), vec![later]);
+}
+
+#[tokio::test]
+async fn add_snooze_state() {
+    let Some(bus) = TestBus::new("add-snooze-state") else { return };
+    bus.serve(fake_clock()).await;
+
+    let connection = bus.connect().await;
+    let rezz = RezzProxy::new(&connection).await.unwrap();
+
+    // Snooze state sent by clients is reset for new alarms.
+    let mut alarm = owned_alarm("a", START + 60, 30);
+    alarm.snoozed_until = START + 600;
+    alarm.snoozes = 2;
+    rezz.add_alarm(alarm).await.unwrap();
+    assert_eq!(rezz.alarms().await.unwrap(), vec![owned_alarm("a", START + 60, 30)]);
+}
+
+#[tokio::test]
+async fn update_snooze_state() {
+    let Some(bus) = TestBus::new("update-snooze-state") else { return };
+    let clock = fake_clock();
+    bus.serve(clock.clone()).await;
+
+    let connection = bus.connect().await;
+    let rezz = RezzProxy::new(&connection).await.unwrap();
+
+    rezz.add_alarm(owned_alarm("a", START + 60, 30)).await.unwrap();
+    clock.set(OffsetDateTime::from_unix_timestamp(START + 60).unwrap());
+    rezz.snooze_alarm("a".into(), 5).await.unwrap();
+
+    // Updates keep the stored snooze state, ignoring the client's.
+    let mut updated = owned_alarm("a", START + 60, 30);
+    updated.label = "Updated".into();
+    rezz.update_alarm(updated.clone()).await.unwrap();
+
+    updated.snoozed_until = START + 60 + 5 * 60;
+    updated.snoozes = 1;
+    assert_eq!(rezz.alarms().await.unwrap(), vec![updated]);
+
+    // Moving the alarm to a new time resets its snooze state.
+    let mut moved = owned_alarm("a", START + 3600, 30);
+    moved.snoozed_until = START + 600;
+    moved.snoozes = 1;
+    rezz.update_alarm(moved).await.unwrap();
+    assert_eq!(rezz.alarms().await.unwrap(), vec![owned_alarm("a", START + 3600, 30)]);
+}
+
+#[tokio::test]
+async fn enable_disable() {
+    let Some(bus) = TestBus::new("enable-disable") else { return };
+    let wakeup = bus.serve(fake_clock()).await;
+
+    let connection = bus.connect().await;
+    let rezz = RezzProxy::new(&connection).await.unwrap();
+
+    let alarm = owned_alarm("a", START + 60, 30);
+    rezz.add_alarm(alarm.clone()).await.unwrap();
+
+    // Disabled alarms are kept, but not scheduled.
+    rezz.set_alarm_enabled(alarm.id.clone(), false).await.unwrap();
+    let alarms = rezz.alarms().await.unwrap();
+    assert_eq!(alarms.len(), 1);
+    assert!(!alarms[0].enabled);
+    assert_eq!(wakeup.get().unwrap(), None);
+
+    rezz.set_alarm_enabled(alarm.id.clone(), true).await.unwrap();
+    assert_eq!(rezz.alarms().await.unwrap(), vec![alarm.clone()]);
+
+    let wakeup_time = OffsetDateTime::from_unix_timestamp(alarm.unix_time).unwrap();
+    assert_eq!(wakeup.get().unwrap(), Some(wakeup_time));
+}
+
+#[tokio::test]
+async fn duplicate_id() {
+    let Some(bus) = TestBus::new("duplicate-id") else { return };
+    bus.serve(fake_clock()).await;
+
+    let connection = bus.connect().await;
+    let rezz = RezzProxy::new(&connection).await.unwrap();
+
+    let alarm = owned_alarm("a", START + 60, 30);
+    rezz.add_alarm(alarm.clone()).await.unwrap();
+
+    let duplicate = owned_alarm("a", START + 120, 30);
+    let err = rezz.add_alarm(duplicate).await.unwrap_err();
+    assert!(
+        matches!(&err, zbus::Error::MethodError(name, ..) if name.as_str() == "org.catacombing.rezz.Error.AlarmExists"),
+        "unexpected error: {err}"
+    );
+
+    assert_eq!(rezz.alarms().await.unwrap(), vec![alarm]);
+}
+
+#[tokio::test]
+async fn missing_id() {
+    let Some(bus) = TestBus::new("missing-id") else { return };
+    bus.serve(fake_clock()).await;
+
+    let connection = bus.connect().await;
+    let rezz = RezzProxy::new(&connection).await.unwrap();
+
+    let err = rezz.remove_alarm("a".into()).await.unwrap_err();
+    assert!(
+        matches!(&err, zbus::Error::MethodError(name, ..) if name.as_str() == "org.catacombing.rezz.Error.AlarmNotFound"),
+        "unexpected error: {err}"
+    );
+
+    let err = rezz.snooze_alarm("a".into(), 5).await.unwrap_err();
+    assert!(
+        matches!(&err, zbus::Error::MethodError(name, ..) if name.as_str() == "org.catacombing.rezz.Error.AlarmNotFound"),
+        "unexpected error: {err}"
+    );
+}
+
+#[tokio::test]
+async fn validation() {
+    let Some(bus) = TestBus::new("validation") else { return };
+    bus.serve(fake_clock()).await;
+
+    let connection = bus.connect().await;
+    let rezz = RezzProxy::new(&connection).await.unwrap();
+
+    let elapsed = owned_alarm("a", START - 60, 30);
+    let err = rezz.add_alarm(elapsed).await.unwrap_err();
+    assert!(
+        matches!(&err, zbus::Error::MethodError(name, ..) if name.as_str() == "org.catacombing.rezz.Error.ElapsedTime"),
+        "unexpected error: {err}"
+    );
+
+    let long_ringing = owned_alarm("a", START + 60, server::MAX_RING_SECONDS + 1);
+    let err = rezz.add_alarm(long_ringing).await.unwrap_err();
+    assert!(
+        matches!(&err, zbus::Error::MethodError(name, ..) if name.as_str() == "org.catacombing.rezz.Error.RingDuration"),
+        "unexpected error: {err}"
+    );
+
+    // Timestamps outside the supported range are rejected without crashing.
+    for unix_time in [i64::MIN, i64::MAX] {
+        let invalid = owned_alarm("a", unix_time, 30);
+        let err = rezz.add_alarm(invalid).await.unwrap_err();
+        assert!(
+            matches!(&err, zbus::Error::MethodError(name, ..) if name.as_str() == "org.catacombing.rezz.Error.InvalidArgs"),
+            "unexpected error: {err}"
+        );
+    }
+
+    assert_eq!(rezz.alarms().await.unwrap(), Vec::new());
+}
+
+#[tokio::test]
+async fn elapsed_cleanup() {
+    let Some(bus) = TestBus::new("elapsed-cleanup") else { return };
+    let clock = fake_clock();
+    bus.serve(clock.clone()).await;
+
+    let connection = bus.connect().await;
+    let rezz = RezzProxy::new(&connection).await.unwrap();
+    let mut alarms_stream = rezz.receive_alarms_changed().await;
+
+    // Add an alarm which finishes ringing after one second.
+    let alarm = owned_alarm("a", START + 1, 0);
+    rezz.add_alarm(alarm.clone()).await.unwrap();
+    while next_alarms(&mut alarms_stream).await.is_empty() {}
+
+    // Let the alarm elapse before the server's timer fires.
+    clock.advance(Duration::seconds(10));
+    assert_eq!(next_alarms(&mut alarms_stream).await, Vec::new());
+}
+
+#[tokio::test]
+async fn startup_cleanup() {
+    let Some(bus) = TestBus::new("startup-cleanup") else { return };
+
+    let elapsed = r#"{"version":1,"alarms":[{"id":"a","unix_time":1,"ring_seconds":2}]}"#;
+    fs::write(bus.db_path(), elapsed).unwrap();
+    bus.serve(fake_clock()).await;
+
+    let connection = bus.connect().await;
+    let rezz = RezzProxy::new(&connection).await.unwrap();
+
+    // Wait for the server to process its initial cleanup.
+    let mut alarms = rezz.alarms().await.unwrap();
+    for _ in 0..50 {
+        if alarms.is_empty() {
+            break;
+        }
+        tokio_time::sleep(StdDuration::from_millis(100)).await;
+        alarms = rezz.alarms().await.unwrap();
+    }
+    assert_eq!(alarms, Vec::new());
+
+    // Ensure the cleanup was persisted.
+    let db = fs::read_to_string(bus.db_path()).unwrap();
+    assert_eq!(db, r#"{"version":1,"alarms":[]}"#);
+}
+
+#[tokio::test]
+async fn property_changed() {
+    let Some(bus) = TestBus::new("property-changed") else { return };
+    bus.serve(fake_clock()).await;
+
+    let connection = bus.connect().await;
+    let rezz = RezzProxy::new(&connection).await.unwrap();
+    let mut alarms_stream = rezz.receive_alarms_changed().await;
+
+    // Skip initial property value.
+    let mut alarms = next_alarms(&mut alarms_stream).await;
+    assert_eq!(alarms, Vec::new());
+
+    let first = owned_alarm("a", START + 120, 30);
+    rezz.add_alarm(first.clone()).await.unwrap();
+    alarms = next_alarms(&mut alarms_stream).await;
+    assert_eq!(alarms, vec![first.clone()]);
+
+    let second = owned_alarm("b", START + 60, 30);
+    rezz.add_alarm(second.clone()).await.unwrap();
+    alarms = next_alarms(&mut alarms_stream).await;
+    assert_eq!(alarms, vec![first.clone(), second]);
+
+    rezz.remove_alarm("b".into()).await.unwrap();
+    alarms = next_alarms(&mut alarms_stream).await;
+    assert_eq!(alarms, vec![first]);
+}
@@ -1,7 +1,9 @@
 //! Configuration options.
 
 use std::fmt::{self, Display, Formatter};
+use std::time::Duration;
 
+use alarm::audio::{RampShape as AudioRampShape, VolumeRamp};
 use calloop::LoopHandle;
 use calloop::channel::{self, Event, Sender};
 use configory::EventHandler;
@@ -9,6 +11,7 @@ use configory::docgen::{DocType, Docgen, Leaf};
 use serde::de::Visitor;
 use serde::{Deserialize, Deserializer};
 use skia_safe::Color4f;
+use smithay_client_toolkit::seat::keyboard::Keysym;
 use tracing::{error, info};
 
 use crate::State;
@@ -35,6 +38,8 @@ pub struct Config {
     pub colors: Colors,
     /// This section documents the `[input]` table.
     pub input: Input,
+    /// This section documents the `[alarm]` table.
+    pub alarm: Alarm,
 }
 
 /// Font configuration.
@@ -89,7 +94,7 @@ impl Default for Colors {
 }
 
 /// Input configuration.
-#[derive(Docgen, Deserialize, PartialEq, Copy, Clone, Debug)]
+#[derive(Docgen, Deserialize, PartialEq, Clone, Debug)]
 #[serde(default, deny_unknown_fields)]
 pub struct Input {
     /// Square of the maximum distance before touch input is considered a drag.
@@ -104,6 +109,31 @@ pub struct Input {
     pub quick_minutes_1: u16,
     /// Minutes for quick action 2.
     pub quick_minutes_2: u16,
+
+    /// Keys scrolling up or selecting the item above.
+    pub key_up: Keys,
+    /// Keys scrolling down or selecting the item below.
+    pub key_down: Keys,
+    /// Keys scrolling up by a page.
+    pub key_page_up: Keys,
+    /// Keys scrolling down by a page.
+    pub key_page_down: Keys,
+    /// Keys focusing the next element.
+    pub key_next: Keys,
+    /// Keys focusing the previous element.
+    pub key_previous: Keys,
+    /// Keys activating the focused element.
+    pub key_confirm: Keys,
+    /// Keys returning to the previous view.
+    pub key_back: Keys,
+    /// Keys removing the focused alarm.
+    pub key_delete: Keys,
+    /// Keys enabling or disabling the focused alarm.
+    pub key_toggle: Keys,
+    /// Keys stopping a ringing alarm.
+    pub key_stop: Keys,
+    /// Keys snoozing a ringing alarm.
+    pub key_snooze: Keys,
 }
 
 impl Default for Input {
@@ -114,6 +144,280 @@ impl Default for Input {
             velocity_interval: 30,
             quick_minutes_2: 480,
             quick_minutes_1: 90,
+            key_page_down: Keys::new([Key::Named(Keysym::Page_Down)]),
+            key_page_up: Keys::new([Key::Named(Keysym::Page_Up)]),
+            key_next: Keys::new([Key::Named(Keysym::Right), Key::Named(Keysym::Tab)]),
+            key_confirm: Keys::new([Key::Named(Keysym::Return)]),
+            key_previous: Keys::new([Key::Named(Keysym::Left)]),
+            key_delete: Keys::new([Key::Named(Keysym::Delete)]),
+            key_toggle: Keys::new([Key::Char('t')]),
+            key_back: Keys::new([Key::Named(Keysym::Escape)]),
+            key_down: Keys::new([Key::Named(Keysym::Down)]),
+            key_stop: Keys::new([Key::Named(Keysym::space)]),
+            key_up: Keys::new([Key::Named(Keysym::Up)]),
+            key_snooze: Keys::new([Key::Char('s')]),
+        }
+    }
+}
+
+impl Input {
+    /// Get the action bound to a key.
+    pub fn key_action(&self, keysym: Keysym, text: Option<&str>) -> Option<KeyAction> {
+        let bindings = [
+            (&self.key_up, KeyAction::Up),
+            (&self.key_down, KeyAction::Down),
+            (&self.key_page_up, KeyAction::PageUp),
+            (&self.key_page_down, KeyAction::PageDown),
+            (&self.key_next, KeyAction::Next),
+            (&self.key_previous, KeyAction::Previous),
+            (&self.key_confirm, KeyAction::Confirm),
+            (&self.key_back, KeyAction::Back),
+            (&self.key_delete, KeyAction::Delete),
+            (&self.key_toggle, KeyAction::Toggle),
+            (&self.key_stop, KeyAction::Stop),
+            (&self.key_snooze, KeyAction::Snooze),
+        ];
+
+        bindings.into_iter().find(|(keys, _)| keys.matches(keysym, text)).map(|(_, action)| action)
+    }
+}
+
+/// Actions bindable to keyboard keys.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum KeyAction {
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Next,
+    Previous,
+    Confirm,
+    Back,
+    Delete,
+    Toggle,
+    Stop,
+    Snooze,
+}
+
+/// Alarm configuration.
+#[derive(Docgen, Deserialize, PartialEq, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Alarm {
+    /// Minutes a ringing alarm is delayed by when snoozed.
+    pub snooze_minutes: u16,
+    /// Maximum number of times an alarm can be snoozed.
+    pub max_snoozes: u16,
+    /// Default sound file, empty for the built-in sound.
+    pub sound: String,
+
+    /// Volume percentage at the start of the alarm.
+    pub volume_start: u8,
+    /// Volume percentage at the end of the volume ramp.
+    pub volume_target: u8,
+    /// Seconds until the target volume is reached.
+    pub ramp_seconds: u16,
+    /// Volume ramp curve (linear or logarithmic).
+    pub ramp_shape: RampShape,
+
+    /// Vibration pattern of alternating on/off milliseconds, empty to disable.
+    pub vibration: String,
+    /// Disable the alarm sound, for vibration-only alarms.
+    pub silent: bool,
+    /// Show ringing alarms above the lock screen and other windows.
+    pub overlay: bool,
+}
+
+impl Default for Alarm {
+    fn default() -> Self {
+        Self {
+            snooze_minutes: 10,
+            volume_target: 100,
+            volume_start: 100,
+            max_snoozes: 3,
+            overlay: true,
+            ramp_seconds: Default::default(),
+            ramp_shape: Default::default(),
+            vibration: Default::default(),
+            silent: Default::default(),
+            sound: Default::default(),
+        }
+    }
+}
+
+impl Alarm {
+    /// Get the alarm's volume ramp.
+    pub fn volume_ramp(&self) -> VolumeRamp {
+        VolumeRamp {
+            duration: Duration::from_secs(self.ramp_seconds.into()),
+            shape: self.ramp_shape.into(),
+            target: self.volume_target,
+            start: self.volume_start,
+        }
+    }
+}
+
+/// Volume ramp curve.
+#[derive(Deserialize, Copy, Clone, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum RampShape {
+    #[default]
+    Linear,
+    Logarithmic,
+}
+
+impl Docgen for RampShape {
+    fn doc_type() -> DocType {
+        DocType::Leaf(Leaf::new("text"))
+    }
+
+    fn format(&self) -> String {
+        format!("\"{}\"", AudioRampShape::from(*self))
+    }
+}
+
+impl From<RampShape> for AudioRampShape {
+    fn from(shape: RampShape) -> Self {
+        match shape {
+            RampShape::Linear => Self::Linear,
+            RampShape::Logarithmic => Self::Logarithmic,
+        }
+    }
+}
+
+/// Names of non-character keys.
+const NAMED_KEYS: [(&str, Keysym); 14] = [
+    ("Up", Keysym::Up),
+    ("Down", Keysym::Down),
+    ("Left", Keysym::Left),
+    ("Right", Keysym::Right),
+    ("PageUp", Keysym::Page_Up),
+    ("PageDown", Keysym::Page_Down),
+    ("Home", Keysym::Home),
+    ("End", Keysym::End),
+    ("Tab", Keysym::Tab),
+    ("Enter", Keysym::Return),
+    ("Escape", Keysym::Escape),
+    ("Delete", Keysym::Delete),
+    ("BackSpace", Keysym::BackSpace),
+    ("Space", Keysym::space),
+];
+
+/// Comma-separated list of keyboard keys.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Keys(Vec<Key>);
+
+impl Keys {
+    fn new<const N: usize>(keys: [Key; N]) -> Self {
+        Self(keys.into())
+    }
+
+    /// Check whether a key press matches any of the keys.
+    pub fn matches(&self, keysym: Keysym, text: Option<&str>) -> bool {
+        self.0.iter().any(|key| key.matches(keysym, text))
+    }
+}
+
+impl Docgen for Keys {
+    fn doc_type() -> DocType {
+        DocType::Leaf(Leaf::new("text"))
+    }
+
+    fn format(&self) -> String {
+        format!("\"{self}\"")
+    }
+}
+
+/// Deserialize keys from a comma-separated string like `Right,Tab`.
+impl<'de> Deserialize<'de> for Keys {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct KeysVisitor;
+
+        impl Visitor<'_> for KeysVisitor {
+            type Value = Keys;
+
+            fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                f.write_str("comma-separated keys like \"Right,Tab\"")
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<Keys, E>
+            where
+                E: serde::de::Error,
+            {
+                let keys = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|key| !key.is_empty())
+                    .map(|key| {
+                        Key::from_name(key).ok_or_else(|| E::custom(format!("unknown key {key:?}")))
+                    })
+                    .collect::<Result<_, _>>()?;
+                Ok(Keys(keys))
+            }
+        }
+
+        deserializer.deserialize_str(KeysVisitor)
+    }
+}
+
+impl Display for Keys {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (i, key) in self.0.iter().enumerate() {
+            if i != 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{key}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Keyboard key.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum Key {
+    /// Non-character key, like `Enter`.
+    Named(Keysym),
+    /// Lowercase character key.
+    Char(char),
+}
+
+impl Key {
+    /// Parse a key from its name or character.
+    fn from_name(name: &str) -> Option<Self> {
+        if let Some((_, keysym)) = NAMED_KEYS.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)) {
+            return Some(Self::Named(*keysym));
+        }
+
+        let mut chars = name.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Some(Self::Char(c.to_ascii_lowercase())),
+            _ => None,
+        }
+    }
+
+    /// Check whether a key press matches this key.
+    fn matches(&self, keysym: Keysym, text: Option<&str>) -> bool {
+        match self {
+            Self::Named(Keysym::Return) => matches!(keysym, Keysym::Return | Keysym::KP_Enter),
+            Self::Named(named) => *named == keysym,
+            Self::Char(c) => text.is_some_and(|text| {
+                let mut chars = text.chars().map(|c| c.to_ascii_lowercase());
+                chars.next() == Some(*c) && chars.next().is_none()
+            }),
+        }
+    }
+}
+
+impl Display for Key {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Named(keysym) => {
+                let name = NAMED_KEYS.iter().find(|(_, k)| k == keysym).map_or("", |(n, _)| n);
+                f.write_str(name)
+            },
+            Self::Char(c) => write!(f, "{}", c.to_ascii_uppercase()),
         }
     }
 }
@@ -261,6 +565,8 @@ mod tests {
     use std::fs;
 
     use configory::docgen::markdown::Markdown;
+    use serde::de::IntoDeserializer;
+    use serde::de::value::{Error as ValueError, StrDeserializer};
 
     use super::*;
 
@@ -277,4 +583,18 @@ mod tests {
         let docs = fs::read_to_string("./docs/config.md").unwrap();
         assert_eq!(docs, expected);
     }
+
+    #[test]
+    fn parse_keys() {
+        let deserializer: StrDeserializer<'_, ValueError> = "enter, s".into_deserializer();
+        let keys = Keys::deserialize(deserializer).unwrap();
+        assert_eq!(keys.to_string(), "Enter,S");
+
+        assert!(keys.matches(Keysym::KP_Enter, None));
+        assert!(keys.matches(Keysym::S, Some("S")));
+        assert!(!keys.matches(Keysym::space, Some(" ")));
+
+        let deserializer: StrDeserializer<'_, ValueError> = "Enter,Hyper".into_deserializer();
+        assert!(Keys::deserialize(deserializer).is_err());
+    }
 }
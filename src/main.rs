@@ -1,13 +1,16 @@
 use std::{env, process, thread};
 
-use alarm::{Event as AlarmEvent, Subscriber};
+use alarm::Event as AlarmEvent;
+use alarm::client::{BusAddress, Client};
 use calloop::channel::Event as ChannelEvent;
 use calloop::{EventLoop, LoopHandle, channel};
 use calloop_wayland_source::WaylandSource;
+use clap::Parser;
 use configory::{Manager as ConfigManager, Options as ConfigOptions};
 use smithay_client_toolkit::reexports::client::globals::{
     self, BindError, GlobalError, GlobalList,
 };
+use smithay_client_toolkit::reexports::client::protocol::wl_keyboard::WlKeyboard;
 use smithay_client_toolkit::reexports::client::protocol::wl_pointer::WlPointer;
 use smithay_client_toolkit::reexports::client::protocol::wl_touch::WlTouch;
 use smithay_client_toolkit::reexports::client::{
@@ -20,7 +23,7 @@ use tracing_subscriber::{EnvFilter, FmtSubscriber};
 
 use crate::config::{Config, ConfigEventHandler};
 use crate::ui::window::Window;
-use crate::wayland::ProtocolStates;
+use crate::wayland::{PointerState, ProtocolStates};
 
 mod config;
 mod geometry;
@@ -32,8 +35,23 @@ mod gl {
     include!(concat!(env!("OUT_DIR"), "/gl_bindings.rs"));
 }
 
+/// Alarm clock.
+#[derive(Parser, Debug)]
+#[command(author, version, about)]
+struct Cli {
+    /// Start without a window, only showing it when an alarm rings.
+    #[clap(long)]
+    background: bool,
+
+    /// DBus message bus of the rezz daemon (system, session, or a bus address).
+    #[clap(long, default_value = "system")]
+    bus: BusAddress,
+}
+
 #[tokio::main]
 async fn main() {
+    let cli = Cli::parse();
+
     // Setup logging.
     let directives = env::var("RUST_LOG").unwrap_or("warn,aevum=info,configory=info".into());
     let env_filter = EnvFilter::builder().parse_lossy(directives);
@@ -41,20 +59,21 @@ async fn main() {
 
     info!("Started Aevum");
 
-    if let Err(err) = run().await {
+    if let Err(err) = run(cli).await {
         error!("[CRITICAL] {err}");
         process::exit(1);
     }
 }
 
-async fn run() -> Result<(), Error> {
+async fn run(cli: Cli) -> Result<(), Error> {
     // Initialize Wayland connection.
     let connection = Connection::connect_to_env()?;
     let (globals, queue) = globals::registry_queue_init(&connection)?;
 
     let mut event_loop = EventLoop::try_new()?;
     let mut state =
-        State::new(&event_loop.handle(), connection.clone(), &globals, queue.handle()).await?;
+        State::new(&event_loop.handle(), connection.clone(), &globals, queue.handle(), &cli)
+            .await?;
 
     // Insert wayland source into calloop loop.
     let wayland_source = WaylandSource::new(connection, queue);
@@ -72,13 +91,17 @@ async fn run() -> Result<(), Error> {
 struct State {
     protocol_states: ProtocolStates,
 
+    keyboard: Option<WlKeyboard>,
     pointer: Option<WlPointer>,
+    pointer_state: PointerState,
     touch: Option<WlTouch>,
+    touch_id: Option<i32>,
 
     window: Window,
     config: Config,
 
     terminated: bool,
+    background: bool,
 
     _config_manager: ConfigManager,
 }
@@ -89,6 +112,7 @@ impl State {
         connection: Connection,
         globals: &GlobalList,
         queue: QueueHandle<Self>,
+        cli: &Cli,
     ) -> Result<Self, Error> {
         let protocol_states = ProtocolStates::new(globals, &queue)?;
 
@@ -103,25 +127,39 @@ impl State {
             .flatten()
             .unwrap_or_default();
 
+        // Share a single DBus connection for all alarm requests.
+        let client = Client::new(cli.bus.clone());
+
         // Create the Wayland window.
-        let window = Window::new(&protocol_states, connection, queue, &config)?;
+        let window = Window::new(
+            &protocol_states,
+            connection,
+            queue,
+            &config,
+            client.clone(),
+            cli.background,
+        )?;
 
         // Listen for changes to pending alarms.
-        Self::spawn_listener(event_loop)?;
+        Self::spawn_listener(event_loop, client)?;
 
         Ok(Self {
             protocol_states,
             config,
             window,
+            background: cli.background,
             _config_manager: config_manager,
             terminated: Default::default(),
+            keyboard: Default::default(),
             pointer: Default::default(),
+            pointer_state: Default::default(),
             touch: Default::default(),
+            touch_id: Default::default(),
         })
     }
 
     /// Create a new thread to listen for DBus events.
-    fn spawn_listener(event_loop: &LoopHandle<'static, Self>) -> Result<(), Error> {
+    fn spawn_listener(event_loop: &LoopHandle<'static, Self>, client: Client) -> Result<(), Error> {
         let rt = RuntimeBuilder::new_current_thread().enable_all().build().unwrap();
         let (alarms_tx, alarms_rx) = channel::channel();
 
@@ -133,7 +171,7 @@ impl State {
         thread::spawn(move || {
             let local_set = LocalSet::new();
             local_set.spawn_local(async move {
-                let mut subscriber = match Subscriber::new().await {
+                let mut subscriber = match client.subscribe().await {
                     Ok(subscriber) => subscriber,
                     Err(err) => {
                         error!("Failed to create DBus listener: {err}");
@@ -145,6 +183,11 @@ impl State {
                 let alarms = subscriber.alarms().to_vec();
                 let _ = alarms_tx.send(AlarmEvent::AlarmsChanged(alarms.into()));
 
+                // Report rezz's initial availability.
+                if !subscriber.connected() {
+                    let _ = alarms_tx.send(AlarmEvent::Disconnected);
+                }
+
                 // Handle next alarm event.
                 loop {
                     if let Some(event) = subscriber.next().await {
@@ -153,6 +196,8 @@ impl State {
                                 AlarmEvent::AlarmsChanged(alarms.to_vec().into())
                             },
                             AlarmEvent::Ring(alarm) => AlarmEvent::Ring(alarm),
+                            AlarmEvent::Connected => AlarmEvent::Connected,
+                            AlarmEvent::Disconnected => AlarmEvent::Disconnected,
                         };
                         let _ = alarms_tx.send(event);
                     }
@@ -166,7 +211,11 @@ impl State {
             ChannelEvent::Msg(AlarmEvent::AlarmsChanged(alarms)) => {
                 state.window.set_alarms(alarms.to_vec());
             },
-            ChannelEvent::Msg(AlarmEvent::Ring(alarm)) => state.window.ring(alarm),
+            ChannelEvent::Msg(AlarmEvent::Ring(alarm)) => {
+                state.window.ring(&state.protocol_states, alarm);
+            },
+            ChannelEvent::Msg(AlarmEvent::Connected) => state.window.set_rezz_available(true),
+            ChannelEvent::Msg(AlarmEvent::Disconnected) => state.window.set_rezz_available(false),
             ChannelEvent::Closed => state.terminated = true,
         })?;
 
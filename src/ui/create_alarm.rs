@@ -1,20 +1,26 @@
 //! Alarm creation UI.
 
 use std::mem;
+use std::sync::Arc;
 
-use alarm::Alarms;
+use alarm::audio::{self, SOUND_METADATA_KEY};
+use alarm::client::Client;
+use alarm::error::Error as AlarmError;
 use rezz::Alarm;
+use rezz::clock::{Clock, SystemClock};
 use skia_safe::textlayout::{ParagraphBuilder, ParagraphStyle, TextAlign};
 use skia_safe::{Canvas, Rect};
-use time::{Duration, OffsetDateTime, Time};
-use tracing::error;
+use smithay_client_toolkit::reexports::protocols::wp::cursor_shape::v1::client::wp_cursor_shape_device_v1::Shape as CursorShape;
+use time::{Duration, OffsetDateTime, PrimitiveDateTime, Time};
+use tracing::{error, warn};
 use uuid::Uuid;
 
-use crate::config::Input;
+use crate::config::{Input, KeyAction};
 use crate::geometry::{Point, Size, rect_contains};
 use crate::ui::window::TouchAction as WindowTouchAction;
 use crate::ui::{
-    BUTTON_HEIGHT, BUTTON_PADDING, Icon, OUTSIDE_PADDING, RenderConfig, ScrollVelocity,
+    BUTTON_HEIGHT, BUTTON_PADDING, Icon, OUTSIDE_PADDING, RenderConfig, ScrollDelta,
+    ScrollVelocity, draw_focus,
 };
 
 /// Width and height of time wheel items at scale 1.
@@ -29,21 +35,38 @@ const RING_DURATION: u32 = 15 * 60;
 /// Size of the hour/time separator colons at scale 1.
 const COLON_SIZE: f64 = 6.;
 
+/// Number of visible carousel items.
+const CAROUSEL_PAGE_SIZE: isize = 3;
+
 /// Alarm creation UI state.
 pub struct CreateAlarm {
     touch_state: TouchState,
+    focus: Option<Focus>,
 
     minute_carousel: TextCarousel,
     hour_carousel: TextCarousel,
 
+    /// Available sound files.
+    sounds: Vec<String>,
+    /// Selected sound, `None` for the default sound.
+    sound_index: Option<usize>,
+
+    /// Existing alarm being edited.
+    editing: Option<Alarm>,
+    /// Exact local time of the edited alarm.
+    edit_time: Option<Time>,
+
+    clock: Arc<dyn Clock>,
+    client: Client,
+
     size: Size<f32>,
     scale: f64,
 
     dirty: bool,
 }
 
-impl Default for CreateAlarm {
-    fn default() -> Self {
+impl CreateAlarm {
+    pub fn new(client: Client) -> Self {
         let hours = (0..24).map(|hour| format!("{hour:0>2}")).collect();
         let hour_carousel = TextCarousel::new(hours);
         let minutes = (0..60).step_by(5).map(|minute| format!("{minute:0>2}")).collect();
@@ -54,13 +77,18 @@ impl Default for CreateAlarm {
             hour_carousel,
             dirty: true,
             scale: 1.,
+            clock: Arc::new(SystemClock),
+            client,
             touch_state: Default::default(),
+            sound_index: Default::default(),
+            editing: Default::default(),
+            edit_time: Default::default(),
+            focus: Default::default(),
+            sounds: Default::default(),
             size: Default::default(),
         }
     }
-}
 
-impl CreateAlarm {
     /// Render current UI state.
     pub fn draw(&mut self, size: Size, scale: f64, canvas: &Canvas, render_config: &RenderConfig) {
         self.dirty = false;
@@ -107,6 +135,42 @@ impl CreateAlarm {
         let confirm_rect = Self::confirm_button_rect(self.size, scale);
         canvas.draw_rect(confirm_rect, &render_config.button_paint);
         Icon::Confirm.draw(canvas, scale, &render_config.icon_paint, confirm_rect);
+
+        // Draw the sound selection button.
+        let sound_rect = Self::sound_button_rect(self.size, scale);
+        canvas.draw_rect(sound_rect, &render_config.button_paint);
+        self.draw_sound_text(canvas, render_config, sound_rect);
+
+        // Draw keyboard focus indicator.
+        if let Some(focus) = self.focus {
+            draw_focus(canvas, render_config, focus.rect(self.size, scale));
+        }
+    }
+
+    /// Draw the selected sound's name.
+    fn draw_sound_text(&self, canvas: &Canvas, render_config: &RenderConfig, rect: Rect) {
+        let text = match self.sound() {
+            Some(sound) => sound.rsplit_once('.').map_or(sound, |(name, _)| name),
+            None => "Default Sound",
+        };
+
+        // Setup text style, truncating long file names.
+        let mut paragraph_style = ParagraphStyle::new();
+        paragraph_style.set_text_style(&render_config.text_style);
+        paragraph_style.set_text_align(TextAlign::Center);
+        paragraph_style.set_ellipsis("…");
+        paragraph_style.set_max_lines(1);
+
+        // Create and layout the paragraph.
+        let padding = (BUTTON_PADDING * self.scale) as f32;
+        let mut paragraph_builder = ParagraphBuilder::new(&paragraph_style, &render_config.fonts);
+        paragraph_builder.add_text(text);
+        let mut paragraph = paragraph_builder.build();
+        paragraph.layout(rect.right - rect.left - 2. * padding);
+
+        // Center paragraph vertically inside its rect.
+        let y_offset = (rect.bottom - rect.top - paragraph.height()) / 2.;
+        paragraph.paint(canvas, Point::new(rect.left + padding, rect.top + y_offset));
     }
 
     /// Draw text centered within a rectangle.
@@ -141,8 +205,7 @@ impl CreateAlarm {
     /// Reset the time selection wheels to the time five minutes from now.
     pub fn reset(&mut self) {
         // Get current time.
-        let now = OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc());
-        let mut time = now.time();
+        let mut time = self.clock.now_local().time();
 
         // Add five minutes to ensure time is in the future.
         time += Duration::minutes(5);
@@ -150,10 +213,42 @@ impl CreateAlarm {
         // Scroll to the time one minute from now.
         self.minute_carousel.scroll_to(time.minute() as usize / 5);
         self.hour_carousel.scroll_to(time.hour() as usize);
+
+        // Reload available sounds and reset to the default sound.
+        self.sounds = audio::sounds();
+        self.sound_index = None;
+
+        self.editing = None;
+        self.edit_time = None;
+        self.focus = None;
+    }
+
+    /// Reset the UI state to edit an existing alarm.
+    ///
+    /// Minutes are rounded down to the minute wheel's five minute steps, but
+    /// the exact time is kept unless the time selection wheels are moved.
+    pub fn edit(&mut self, alarm: Alarm) {
+        self.reset();
+
+        // Scroll to the alarm's regular local time, ignoring snoozes.
+        let time = OffsetDateTime::UNIX_EPOCH + Duration::seconds(alarm.unix_time);
+        let local_time = time.to_offset(self.clock.local_offset_at(time));
+        self.minute_carousel.scroll_to(local_time.minute() as usize / 5);
+        self.hour_carousel.scroll_to(local_time.hour() as usize);
+        self.edit_time = Some(local_time.time());
+
+        // Select the alarm's sound.
+        let sound = alarm.metadata.get(SOUND_METADATA_KEY);
+        self.sound_index = sound.and_then(|sound| self.sounds.iter().position(|s| s == sound));
+
+        self.editing = Some(alarm);
     }
 
     /// Handle touch press.
     pub fn touch_down(&mut self, logical_point: Point<f64>) {
+        // Hide keyboard focus during touch input.
+        self.dirty |= self.focus.take().is_some();
+
         // Convert position to physical space.
         let point = logical_point * self.scale;
         self.touch_state.point = point;
@@ -166,11 +261,14 @@ impl CreateAlarm {
         let quick_rect_2 = Self::quick_action_rect_2(self.size, self.scale);
         let hour_rect = Self::hour_carousel_rect(self.size, self.scale);
         let back_rect = Self::back_button_rect(self.size, self.scale);
+        let sound_rect = Self::sound_button_rect(self.size, self.scale);
 
         if rect_contains(confirm_rect, point) {
             self.touch_state.action = TouchAction::Confirm;
         } else if rect_contains(back_rect, point) {
             self.touch_state.action = TouchAction::Back;
+        } else if rect_contains(sound_rect, point) {
+            self.touch_state.action = TouchAction::Sound;
         } else if rect_contains(minute_rect, point) {
             self.touch_state.action = TouchAction::MinuteCarousel;
 
@@ -215,23 +313,19 @@ impl CreateAlarm {
             TouchAction::Confirm => {
                 let rect = Self::confirm_button_rect(self.size, self.scale);
                 if rect_contains(rect, self.touch_state.point) {
-                    // Get alarm time as unix timestamp.
-                    let alarm_time = self.alarm_time();
-                    let unix_time = (alarm_time - OffsetDateTime::UNIX_EPOCH).whole_seconds();
-
-                    // Stage new alarm.
-                    let id = Uuid::new_v4().to_string();
-                    let alarm = Alarm::new(&id, unix_time, RING_DURATION);
-                    tokio::spawn(async {
-                        if let Err(err) = Alarms.add(alarm).await {
-                            error!("Failed to create alarm: {err}");
-                        }
-                    });
+                    self.save_alarm();
 
                     // Return to the list view.
                     return WindowTouchAction::ListAlarmsView;
                 }
             },
+            // Cycle through the available sounds.
+            TouchAction::Sound => {
+                let rect = Self::sound_button_rect(self.size, self.scale);
+                if rect_contains(rect, self.touch_state.point) {
+                    self.next_sound();
+                }
+            },
             // Add 90 minutes to the current alarm.
             TouchAction::QuickAction1 => self.add_minutes(input_config.quick_minutes_1),
             // Add 8 hours to the current alarm.
@@ -244,6 +338,181 @@ impl CreateAlarm {
         WindowTouchAction::None
     }
 
+    /// Handle touch cancellation.
+    pub fn touch_cancel(&mut self) {
+        self.touch_state = Default::default();
+
+        // Allow carousels to snap to the nearest item.
+        self.minute_carousel.touch_up();
+        self.hour_carousel.touch_up();
+    }
+
+    /// Handle pointer axis scrolling.
+    pub fn scroll(&mut self, logical_point: Point<f64>, delta: ScrollDelta) {
+        let point = logical_point * self.scale;
+
+        // Find the carousel below the pointer.
+        let hour_rect = Self::hour_carousel_rect(self.size, self.scale);
+        let minute_rect = Self::minute_carousel_rect(self.size, self.scale);
+        let carousel = if rect_contains(hour_rect, point) {
+            &mut self.hour_carousel
+        } else if rect_contains(minute_rect, point) {
+            &mut self.minute_carousel
+        } else {
+            return;
+        };
+
+        match delta {
+            ScrollDelta::Steps(steps) => carousel.scroll_by(steps as isize),
+            ScrollDelta::Pixels(pixels) => carousel.scroll_pixels(pixels * self.scale),
+        }
+    }
+
+    /// Get the cursor shape at a location.
+    pub fn cursor_shape(&self, logical_point: Point<f64>) -> CursorShape {
+        let point = logical_point * self.scale;
+
+        let carousel_rects = [
+            Self::hour_carousel_rect(self.size, self.scale),
+            Self::minute_carousel_rect(self.size, self.scale),
+        ];
+        let button_rects = [
+            Self::confirm_button_rect(self.size, self.scale),
+            Self::back_button_rect(self.size, self.scale),
+            Self::sound_button_rect(self.size, self.scale),
+            Self::quick_action_rect_1(self.size, self.scale),
+            Self::quick_action_rect_2(self.size, self.scale),
+        ];
+
+        if carousel_rects.into_iter().any(|rect| rect_contains(rect, point)) {
+            CursorShape::Grab
+        } else if button_rects.into_iter().any(|rect| rect_contains(rect, point)) {
+            CursorShape::Pointer
+        } else {
+            CursorShape::Default
+        }
+    }
+
+    /// Handle keyboard input.
+    pub fn key_press(&mut self, input_config: &Input, action: KeyAction) -> WindowTouchAction {
+        self.dirty = true;
+
+        let focus = match (action, self.focus) {
+            (KeyAction::Back, _) => return WindowTouchAction::ListAlarmsView,
+            // Activate the focused element, creating the alarm by default.
+            (KeyAction::Confirm, Some(Focus::Back)) => return WindowTouchAction::ListAlarmsView,
+            (KeyAction::Confirm, Some(Focus::Sound)) => {
+                self.next_sound();
+                return WindowTouchAction::None;
+            },
+            (KeyAction::Confirm, Some(Focus::QuickAction1)) => {
+                self.add_minutes(input_config.quick_minutes_1);
+                return WindowTouchAction::None;
+            },
+            (KeyAction::Confirm, Some(Focus::QuickAction2)) => {
+                self.add_minutes(input_config.quick_minutes_2);
+                return WindowTouchAction::None;
+            },
+            (KeyAction::Confirm, _) => {
+                self.save_alarm();
+                return WindowTouchAction::ListAlarmsView;
+            },
+            // Focus the first element on initial navigation.
+            (_, None) => Focus::HourCarousel,
+            // Scroll the focused carousel.
+            (KeyAction::Up, Some(focus)) if focus.is_carousel() => self.scroll_carousel(focus, -1),
+            (KeyAction::Down, Some(focus)) if focus.is_carousel() => self.scroll_carousel(focus, 1),
+            (KeyAction::PageUp, Some(focus)) if focus.is_carousel() => {
+                self.scroll_carousel(focus, -CAROUSEL_PAGE_SIZE)
+            },
+            (KeyAction::PageDown, Some(focus)) if focus.is_carousel() => {
+                self.scroll_carousel(focus, CAROUSEL_PAGE_SIZE)
+            },
+            // Move focus between elements.
+            (KeyAction::Up | KeyAction::PageUp | KeyAction::Previous, Some(focus)) => {
+                focus.offset(-1)
+            },
+            (KeyAction::Down | KeyAction::PageDown | KeyAction::Next, Some(focus)) => {
+                focus.offset(1)
+            },
+            (
+                KeyAction::Delete | KeyAction::Toggle | KeyAction::Stop | KeyAction::Snooze,
+                Some(focus),
+            ) => focus,
+        };
+        self.focus = Some(focus);
+
+        WindowTouchAction::None
+    }
+
+    /// Scroll a carousel by `delta` items.
+    ///
+    /// Returns the carousel's focus target.
+    fn scroll_carousel(&mut self, focus: Focus, delta: isize) -> Focus {
+        match focus {
+            Focus::HourCarousel => self.hour_carousel.scroll_by(delta),
+            Focus::MinuteCarousel => self.minute_carousel.scroll_by(delta),
+            _ => (),
+        }
+        focus
+    }
+
+    /// Create an alarm at the selected time, or update the edited alarm.
+    fn save_alarm(&self) {
+        // Get alarm time as unix timestamp.
+        let alarm_time = self.alarm_time();
+        let unix_time = (alarm_time - OffsetDateTime::UNIX_EPOCH).whole_seconds();
+
+        // Update edited alarms in place, to keep their ID and other properties.
+        if let Some(editing) = &self.editing {
+            let mut alarm = editing.clone();
+            alarm.unix_time = unix_time;
+            match self.sound() {
+                Some(sound) => alarm.metadata.insert(SOUND_METADATA_KEY.into(), sound.into()),
+                None => alarm.metadata.remove(SOUND_METADATA_KEY),
+            };
+
+            let client = self.client.clone();
+            tokio::spawn(async move {
+                match client.update(alarm.clone()).await {
+                    // Recreate alarms which were removed while editing.
+                    Err(AlarmError::AlarmNotFound(id)) => {
+                        warn!("Edited alarm {id:?} is gone, adding it again");
+                        if let Err(err) = client.add(alarm).await {
+                            error!("Failed to create alarm: {err}");
+                        }
+                    },
+                    Err(err) => error!("Failed to update alarm: {err}"),
+                    Ok(()) => (),
+                }
+            });
+            return;
+        }
+
+        // Stage new alarm.
+        let id = Uuid::new_v4().to_string();
+        let mut alarm = Alarm::new(&id, unix_time, RING_DURATION);
+        if let Some(sound) = self.sound() {
+            alarm.metadata.insert(SOUND_METADATA_KEY.into(), sound.into());
+        }
+        let client = self.client.clone();
+        tokio::spawn(async move {
+            if let Err(err) = client.add(alarm).await {
+                error!("Failed to create alarm: {err}");
+            }
+        });
+    }
+
+    /// Cycle through the available sounds.
+    fn next_sound(&mut self) {
+        self.sound_index = match self.sound_index {
+            Some(index) if index + 1 < self.sounds.len() => Some(index + 1),
+            None if !self.sounds.is_empty() => Some(0),
+            _ => None,
+        };
+        self.dirty = true;
+    }
+
     /// Physical rectangle of the cancel button.
     fn back_button_rect(size: Size<f32>, scale: f64) -> Rect {
         let button_size = (BUTTON_HEIGHT * scale) as f32;
@@ -266,6 +535,18 @@ impl CreateAlarm {
         Rect::new(x, y, x + button_size, y + button_size)
     }
 
+    /// Physical rectangle of the sound selection button.
+    fn sound_button_rect(size: Size<f32>, scale: f64) -> Rect {
+        let confirm_rect = Self::confirm_button_rect(size, scale);
+        let back_rect = Self::back_button_rect(size, scale);
+        let padding = (BUTTON_PADDING * scale) as f32;
+
+        let left = back_rect.right + padding;
+        let right = confirm_rect.left - padding;
+
+        Rect::new(left, back_rect.top, right, back_rect.bottom)
+    }
+
     /// Physical rectangle of the left quick action button.
     fn quick_action_rect_1(size: Size<f32>, scale: f64) -> Rect {
         let back_rect = Self::back_button_rect(size, scale);
@@ -351,7 +632,7 @@ impl CreateAlarm {
     /// Text label for delta between current and alarm time.
     fn delta_text(&self) -> String {
         // Get current and alarm time.
-        let now = OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc());
+        let now = self.clock.now();
         let alarm_time = self.alarm_time();
 
         // Get hours/minutes until alarm.
@@ -385,17 +666,23 @@ impl CreateAlarm {
         let minute = self.minute_carousel.value();
         let hour = self.hour_carousel.value();
 
-        let time = Time::from_hms(hour, minute, 0).unwrap();
+        // Keep the edited alarm's exact time while the wheels are unchanged.
+        let edit_time = self.edit_time.filter(|t| t.hour() == hour && t.minute() / 5 * 5 == minute);
+        let time = edit_time.unwrap_or_else(|| Time::from_hms(hour, minute, 0).unwrap());
 
         // Get next occurrence of the specified time.
-        let mut date_time =
-            OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc());
-        if time < date_time.time() {
-            date_time += Duration::days(1);
+        let now = self.clock.now_local();
+        let mut date = now.date();
+        if time < now.time() {
+            date = date.next_day().unwrap();
         }
-        date_time = date_time.replace_time(time);
 
-        date_time
+        self.clock.assume_local(PrimitiveDateTime::new(date, time))
+    }
+
+    /// Get the selected sound file name.
+    fn sound(&self) -> Option<&str> {
+        self.sound_index.and_then(|index| self.sounds.get(index)).map(String::as_str)
     }
 
     /// Add `interval` minutes to the current alarm.
@@ -430,6 +717,57 @@ enum TouchAction {
     HourCarousel,
     QuickAction1,
     QuickAction2,
+    Sound,
+}
+
+/// Keyboard focus target.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum Focus {
+    HourCarousel,
+    MinuteCarousel,
+    QuickAction1,
+    QuickAction2,
+    Back,
+    Sound,
+    Confirm,
+}
+
+impl Focus {
+    /// Focus targets in navigation order.
+    const ORDER: [Self; 7] = [
+        Self::HourCarousel,
+        Self::MinuteCarousel,
+        Self::QuickAction1,
+        Self::QuickAction2,
+        Self::Back,
+        Self::Sound,
+        Self::Confirm,
+    ];
+
+    /// Get the focus target `delta` elements away from this one.
+    fn offset(self, delta: isize) -> Self {
+        let index = Self::ORDER.iter().position(|focus| *focus == self).unwrap_or_default();
+        let index = (index as isize + delta).rem_euclid(Self::ORDER.len() as isize);
+        Self::ORDER[index as usize]
+    }
+
+    /// Check whether this focus target is a time selection wheel.
+    fn is_carousel(self) -> bool {
+        matches!(self, Self::HourCarousel | Self::MinuteCarousel)
+    }
+
+    /// Physical rectangle of the focus target.
+    fn rect(self, size: Size<f32>, scale: f64) -> Rect {
+        match self {
+            Self::HourCarousel => CreateAlarm::hour_carousel_rect(size, scale),
+            Self::MinuteCarousel => CreateAlarm::minute_carousel_rect(size, scale),
+            Self::QuickAction1 => CreateAlarm::quick_action_rect_1(size, scale),
+            Self::QuickAction2 => CreateAlarm::quick_action_rect_2(size, scale),
+            Self::Back => CreateAlarm::back_button_rect(size, scale),
+            Self::Sound => CreateAlarm::sound_button_rect(size, scale),
+            Self::Confirm => CreateAlarm::confirm_button_rect(size, scale),
+        }
+    }
 }
 
 /// A text item list with infinite scrolling.
@@ -438,6 +776,7 @@ pub struct TextCarousel {
     touch_point: Point<f64>,
     touch_active: bool,
     scroll_offset: f64,
+    pixel_scroll: f64,
 
     items: Vec<String>,
 
@@ -452,6 +791,7 @@ impl TextCarousel {
             dirty: true,
             scale: 1.,
             scroll_offset: Default::default(),
+            pixel_scroll: Default::default(),
             touch_active: Default::default(),
             touch_point: Default::default(),
             velocity: Default::default(),
@@ -575,13 +915,16 @@ impl TextCarousel {
 
     /// Get the selected value.
     fn value(&self) -> u8 {
+        // Parse item as number.
+        str::parse(&self.items[self.index()]).unwrap()
+    }
+
+    /// Get the selected item's index.
+    fn index(&self) -> usize {
         // Calculate index based on current offset.
         let item_height = CAROUSEL_ITEM_SIZE * self.scale;
         let index = (-self.scroll_offset / item_height).round() as isize;
-        let index = index.rem_euclid(self.items.len() as isize) as usize;
-
-        // Parse item as number.
-        str::parse(&self.items[index]).unwrap()
+        index.rem_euclid(self.items.len() as isize) as usize
     }
 
     /// Scroll to the item at the specified index.
@@ -591,6 +934,31 @@ impl TextCarousel {
         self.dirty = true;
     }
 
+    /// Scroll by `delta` items.
+    fn scroll_by(&mut self, delta: isize) {
+        // Cancel velocity to prevent scrolling past the target.
+        self.velocity.set(0.);
+
+        let index = (self.index() as isize + delta).rem_euclid(self.items.len() as isize);
+        self.scroll_to(index as usize);
+    }
+
+    /// Scroll by a continuous physical distance.
+    ///
+    /// Partial items are accumulated until the distance of an entire item is
+    /// reached, since the carousel always snaps to the nearest item.
+    fn scroll_pixels(&mut self, delta: f64) {
+        self.pixel_scroll += delta;
+
+        let item_height = CAROUSEL_ITEM_SIZE * self.scale;
+        let items = (self.pixel_scroll / item_height).trunc();
+        self.pixel_scroll -= items * item_height;
+
+        if items != 0. {
+            self.scroll_by(items as isize);
+        }
+    }
+
     /// Get the nearest item offset.
     fn rounded_offset(&self) -> f64 {
         let item_height = CAROUSEL_ITEM_SIZE * self.scale;
@@ -605,3 +973,67 @@ impl TextCarousel {
         offset
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rezz::clock::FakeClock;
+    use time::macros::{datetime, offset};
+
+    use super::*;
+
+    /// Create an alarm creation view with the specified time selected.
+    fn create_alarm(clock: FakeClock, hour: usize, minute: usize) -> CreateAlarm {
+        let client = Client::default();
+        let mut create_alarm = CreateAlarm { clock: Arc::new(clock), ..CreateAlarm::new(client) };
+        create_alarm.hour_carousel.scroll_to(hour);
+        create_alarm.minute_carousel.scroll_to(minute / 5);
+        create_alarm
+    }
+
+    #[test]
+    fn midnight_rollover() {
+        let clock = FakeClock::new(datetime!(2025-10-17 23:50 UTC));
+        let create_alarm = create_alarm(clock, 0, 5);
+
+        assert_eq!(create_alarm.alarm_time(), datetime!(2025-10-18 00:05 UTC));
+        assert_eq!(create_alarm.delta_text(), "in 15 minutes");
+    }
+
+    #[test]
+    fn same_day() {
+        let clock = FakeClock::new(datetime!(2025-10-17 06:30 UTC));
+        let create_alarm = create_alarm(clock, 7, 45);
+
+        assert_eq!(create_alarm.alarm_time(), datetime!(2025-10-17 07:45 UTC));
+        assert_eq!(create_alarm.delta_text(), "in 1 hour and 15 minutes");
+    }
+
+    #[test]
+    fn dst_transition() {
+        let clock = FakeClock::new(datetime!(2025-03-29 23:10 +1));
+        clock.set_offset(OffsetDateTime::UNIX_EPOCH, offset!(+1));
+        clock.set_offset(datetime!(2025-03-30 01:00 UTC), offset!(+2));
+
+        // The night is one hour shorter when switching to DST.
+        let create_alarm = create_alarm(clock, 7, 0);
+        assert_eq!(create_alarm.alarm_time(), datetime!(2025-03-30 07:00 +2));
+        assert_eq!(create_alarm.delta_text(), "in 6 hours and 50 minutes");
+    }
+    #[test]
+    fn edit_exact_minute() {
+        let clock = FakeClock::new(datetime!(2025-10-17 06:30 UTC));
+        let mut create_alarm = create_alarm(clock, 0, 0);
+        let alarm = Alarm::new("edit", datetime!(2025-10-17 07:47 UTC).unix_timestamp(), 0);
+        create_alarm.edit(alarm);
+
+        // Unchanged wheels keep the exact alarm time.
+        assert_eq!(create_alarm.alarm_time(), datetime!(2025-10-17 07:47 UTC));
+
+        // Moving the wheels selects the rounded time.
+        create_alarm.minute_carousel.scroll_by(1);
+        assert_eq!(create_alarm.alarm_time(), datetime!(2025-10-17 07:50 UTC));
+        create_alarm.minute_carousel.scroll_by(-1);
+        create_alarm.hour_carousel.scroll_by(1);
+        assert_eq!(create_alarm.alarm_time(), datetime!(2025-10-17 08:45 UTC));
+    }
+}
@@ -2,19 +2,23 @@
 
 use std::mem;
 
-use alarm::Alarms;
+use alarm::client::Client;
+use alarm::error::Error as AlarmError;
 use rezz::Alarm;
-use skia_safe::textlayout::{ParagraphBuilder, ParagraphStyle};
+use skia_safe::textlayout::{ParagraphBuilder, ParagraphStyle, TextAlign};
 use skia_safe::{Canvas, Rect};
+use smithay_client_toolkit::reexports::protocols::wp::cursor_shape::v1::client::wp_cursor_shape_device_v1::Shape as CursorShape;
 use time::macros::format_description;
 use time::{Duration, OffsetDateTime, UtcOffset};
-use tracing::error;
+use tracing::{debug, error};
 
 use crate::Config;
+use crate::config::KeyAction;
 use crate::geometry::{Point, Size, rect_contains};
 use crate::ui::window::TouchAction as WindowTouchAction;
 use crate::ui::{
-    BUTTON_HEIGHT, BUTTON_PADDING, Icon, OUTSIDE_PADDING, RenderConfig, ScrollVelocity,
+    BUTTON_HEIGHT, BUTTON_PADDING, Icon, OUTSIDE_PADDING, RenderConfig, STROKE_WIDTH, ScrollDelta,
+    ScrollVelocity, draw_focus,
 };
 
 /// Horizontal padding around the alarms list at scale 1.
@@ -26,35 +30,45 @@ const ALARM_HEIGHT: f64 = 80.;
 /// Width and height of the alarm deletion button at scale 1.
 const DELETE_SIZE: f64 = 40.;
 
+/// Width of the alarm enable/disable switch at scale 1.
+const TOGGLE_WIDTH: f64 = 50.;
+
+/// Height of the alarm enable/disable switch at scale 1.
+const TOGGLE_HEIGHT: f64 = 28.;
+
 /// Alarm list UI state.
 pub struct ListAlarms {
     velocity: ScrollVelocity,
     touch_state: TouchState,
     scroll_offset: f64,
+    focus: Option<Focus>,
 
     size: Size<f32>,
     scale: f64,
 
     alarms: Vec<Alarm>,
+    client: Client,
 
+    rezz_available: bool,
     dirty: bool,
 }
 
-impl Default for ListAlarms {
-    fn default() -> Self {
+impl ListAlarms {
+    pub fn new(client: Client) -> Self {
         Self {
+            client,
+            rezz_available: true,
             dirty: true,
             scale: 1.,
             scroll_offset: Default::default(),
             touch_state: Default::default(),
             velocity: Default::default(),
             alarms: Default::default(),
+            focus: Default::default(),
             size: Default::default(),
         }
     }
-}
 
-impl ListAlarms {
     /// Render current UI state.
     pub fn draw(&mut self, size: Size, scale: f64, canvas: &Canvas, render_config: &RenderConfig) {
         self.dirty = false;
@@ -82,9 +96,13 @@ impl ListAlarms {
         let alarms_end = alarm_rect.bottom;
         alarm_rect.top += self.scroll_offset as f32;
         alarm_rect.bottom += self.scroll_offset as f32;
-        for alarm in self.alarms.iter().rev() {
+        for (index, alarm) in self.alarms.iter().enumerate().rev() {
             if alarm_rect.bottom > 0. && alarm_rect.top < alarms_end {
                 self.draw_alarm(canvas, render_config, alarm_rect, alarm);
+
+                if self.focus == Some(Focus::Alarm(index)) {
+                    draw_focus(canvas, render_config, alarm_rect);
+                }
             }
 
             // Advance position to the next alarm location.
@@ -99,6 +117,39 @@ impl ListAlarms {
         let new_rect = Self::new_button_rect(self.size, scale);
         canvas.draw_rect(new_rect, &render_config.button_paint);
         Icon::Plus.draw(canvas, scale, &render_config.icon_paint, new_rect);
+        if self.focus == Some(Focus::NewAlarm) {
+            draw_focus(canvas, render_config, new_rect);
+        }
+
+        // Draw a banner while the alarm daemon is unavailable.
+        if !self.rezz_available {
+            self.draw_banner(canvas, render_config);
+        }
+    }
+
+    /// Draw the daemon unavailability banner.
+    fn draw_banner(&self, canvas: &Canvas, render_config: &RenderConfig) {
+        // Draw banner background.
+        let banner_rect = Self::banner_rect(self.size, self.scale);
+        canvas.draw_rect(banner_rect, &render_config.button_paint);
+
+        // Configure text rendering style.
+        let mut banner_style = ParagraphStyle::new();
+        banner_style.set_text_style(&render_config.text_style);
+        banner_style.set_text_align(TextAlign::Center);
+        banner_style.set_ellipsis("…");
+        banner_style.set_max_lines(1);
+
+        // Perform text shaping and layout.
+        let mut banner_builder = ParagraphBuilder::new(&banner_style, &render_config.fonts);
+        banner_builder.add_text("Alarm daemon unavailable");
+        let mut banner_paragraph = banner_builder.build();
+        banner_paragraph.layout(banner_rect.right - banner_rect.left);
+
+        // Draw label in the center of the banner.
+        let y_offset = (banner_rect.bottom - banner_rect.top - banner_paragraph.height()) / 2.;
+        let point = Point::new(banner_rect.left, banner_rect.top + y_offset);
+        banner_paragraph.paint(canvas, point);
     }
 
     /// Draw a single alarm.
@@ -111,14 +162,32 @@ impl ListAlarms {
         delete_rect.bottom += rect.top;
         Icon::Delete.draw(canvas, self.scale, &render_config.icon_paint, delete_rect);
 
+        // Draw the enable/disable switch.
+        let mut toggle_rect = Self::toggle_alarm_rect(self.size, self.scale);
+        toggle_rect.left += rect.left;
+        toggle_rect.top += rect.top;
+        toggle_rect.right += rect.left;
+        toggle_rect.bottom += rect.top;
+        Self::draw_toggle(canvas, render_config, toggle_rect, alarm.enabled);
+
         // Convert alarm's unix time to local time in HH:MM and YYYY-mm-dd format.
         let utc_offset = UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC);
-        let time = OffsetDateTime::UNIX_EPOCH + Duration::seconds(alarm.unix_time);
+        let time = OffsetDateTime::UNIX_EPOCH + Duration::seconds(alarm.ring_time());
         let local_time = time.to_offset(utc_offset);
         let time_format = format_description!("[hour]:[minute]");
         let time_str = local_time.format(&time_format).unwrap();
         let date_format = format_description!("[year]-[month]-[day]");
-        let date_str = local_time.format(&date_format).unwrap();
+        let mut date_str = local_time.format(&date_format).unwrap();
+
+        // Append recurrence rule for repeating alarms.
+        if alarm.recurrence.is_recurring() {
+            date_str = format!("{date_str} ({})", alarm.recurrence);
+        }
+
+        // Prefix the alarm's label.
+        if !alarm.label.is_empty() {
+            date_str = format!("{} · {date_str}", alarm.label);
+        }
 
         // Create time label paragraph.
 
@@ -137,12 +206,14 @@ impl ListAlarms {
         // Setup text style.
         let mut date_style = ParagraphStyle::new();
         date_style.set_text_style(&render_config.text_style);
+        date_style.set_ellipsis("…");
+        date_style.set_max_lines(1);
 
         // Create and layout the paragraph.
         let mut date_builder = ParagraphBuilder::new(&date_style, &render_config.fonts);
         date_builder.add_text(date_str);
         let mut date_paragraph = date_builder.build();
-        date_paragraph.layout(rect.right - rect.left);
+        date_paragraph.layout(toggle_rect.left - rect.left);
 
         //
 
@@ -156,6 +227,21 @@ impl ListAlarms {
         date_paragraph.paint(canvas, Point::new(rect.left, time_y + time_height));
     }
 
+    /// Draw an alarm's enable/disable switch.
+    fn draw_toggle(canvas: &Canvas, render_config: &RenderConfig, rect: Rect, enabled: bool) {
+        let radius = (rect.bottom - rect.top) / 2.;
+
+        // Draw the switch's track, highlighted while enabled.
+        let track_paint =
+            if enabled { &render_config.highlight_paint } else { &render_config.button_paint };
+        canvas.draw_round_rect(rect, radius, radius, track_paint);
+
+        // Draw the knob on the right while enabled and on the left otherwise.
+        let knob_x = if enabled { rect.right - radius } else { rect.left + radius };
+        let knob_center = Point::new(knob_x, rect.top + radius);
+        canvas.draw_circle(knob_center, radius - STROKE_WIDTH * 2., &render_config.text_paint);
+    }
+
     /// Check whether the UI requires a redraw.
     pub fn dirty(&self) -> bool {
         self.dirty || self.velocity.is_moving()
@@ -166,14 +252,33 @@ impl ListAlarms {
         self.alarms.clear();
         self.alarms.append(&mut alarms);
 
+        // Keep focus within the new alarm list.
+        if let Some(Focus::Alarm(index)) = self.focus
+            && index >= self.alarms.len()
+        {
+            self.focus = match self.alarms.len() {
+                0 => Some(Focus::NewAlarm),
+                len => Some(Focus::Alarm(len - 1)),
+            };
+        }
+
         self.dirty = true;
     }
 
+    /// Update the availability of the alarm daemon.
+    pub fn set_rezz_available(&mut self, available: bool) {
+        self.dirty |= self.rezz_available != available;
+        self.rezz_available = available;
+    }
+
     /// Handle touch press.
     pub fn touch_down(&mut self, logical_point: Point<f64>) {
         // Cancel velocity when a new touch sequence starts.
         self.velocity.set(0.);
 
+        // Hide keyboard focus during touch input.
+        self.dirty |= self.focus.take().is_some();
+
         // Convert position to physical space.
         let point = logical_point * self.scale;
         self.touch_state.point = point;
@@ -181,11 +286,15 @@ impl ListAlarms {
 
         // Get button geometries.
         let new_rect = Self::new_button_rect(self.size, self.scale);
+        let banner_rect = Self::banner_rect(self.size, self.scale);
 
         if rect_contains(new_rect, point) {
             self.touch_state.action = TouchAction::CreateAlarm;
-        } else if let Some((alarm, delete)) = self.alarm_at(point.into()) {
-            self.touch_state.action = TouchAction::AlarmTap(alarm.id.clone(), delete);
+        } else if !self.rezz_available && rect_contains(banner_rect, point) {
+            // Ignore touches on alarms hidden by the banner.
+            self.touch_state.action = TouchAction::None;
+        } else if let Some((alarm, part)) = self.alarm_at(point.into()) {
+            self.touch_state.action = TouchAction::AlarmTap(alarm.id.clone(), part);
         } else {
             self.touch_state.action = TouchAction::None;
         }
@@ -230,12 +339,18 @@ impl ListAlarms {
                 }
             },
             // Remove an alarm.
-            TouchAction::AlarmTap(id, true) => {
-                tokio::spawn(async move {
-                    if let Err(err) = Alarms.remove(id).await {
-                        error!("Failed to remove alarm: {err}");
-                    }
-                });
+            TouchAction::AlarmTap(id, AlarmPart::Delete) => self.remove_alarm(id),
+            // Enable or disable an alarm.
+            TouchAction::AlarmTap(id, AlarmPart::Toggle) => {
+                if let Some(alarm) = self.alarms.iter().find(|alarm| alarm.id == id) {
+                    self.set_alarm_enabled(id, !alarm.enabled);
+                }
+            },
+            // Switch to the alarm view to edit the alarm.
+            TouchAction::AlarmTap(id, AlarmPart::Body) => {
+                if let Some(alarm) = self.alarms.iter().find(|alarm| alarm.id == id) {
+                    return WindowTouchAction::EditAlarmView(alarm.clone());
+                }
             },
             _ => (),
         }
@@ -243,6 +358,159 @@ impl ListAlarms {
         WindowTouchAction::None
     }
 
+    /// Handle touch cancellation.
+    pub fn touch_cancel(&mut self) {
+        self.touch_state = Default::default();
+    }
+
+    /// Handle pointer axis scrolling.
+    pub fn scroll(&mut self, delta: ScrollDelta) {
+        let delta = match delta {
+            ScrollDelta::Steps(steps) => steps as f64 * ALARM_HEIGHT,
+            ScrollDelta::Pixels(pixels) => pixels,
+        };
+
+        // Cancel velocity to avoid fighting the scroll direction.
+        self.velocity.set(0.);
+
+        let old_offset = self.scroll_offset;
+        self.scroll_offset -= delta * self.scale;
+        self.clamp_scroll_offset();
+        self.dirty |= self.scroll_offset != old_offset;
+    }
+
+    /// Get the cursor shape at a location.
+    pub fn cursor_shape(&self, logical_point: Point<f64>) -> CursorShape {
+        let point = logical_point * self.scale;
+
+        let new_rect = Self::new_button_rect(self.size, self.scale);
+        if rect_contains(new_rect, point) {
+            return CursorShape::Pointer;
+        }
+
+        let banner_rect = Self::banner_rect(self.size, self.scale);
+        if !self.rezz_available && rect_contains(banner_rect, point) {
+            return CursorShape::Default;
+        }
+
+        match self.alarm_at(point.into()) {
+            Some(_) => CursorShape::Pointer,
+            None => CursorShape::Default,
+        }
+    }
+
+    /// Handle keyboard input.
+    pub fn key_press(&mut self, action: KeyAction) -> WindowTouchAction {
+        // Cancel velocity when keyboard navigation starts.
+        self.velocity.set(0.);
+
+        match action {
+            KeyAction::Up | KeyAction::Previous => self.move_focus(-1),
+            KeyAction::Down | KeyAction::Next => self.move_focus(1),
+            KeyAction::PageUp => self.move_focus(-(self.page_size() as isize)),
+            KeyAction::PageDown => self.move_focus(self.page_size() as isize),
+            // Switch to the alarm view.
+            KeyAction::Confirm => match self.focus {
+                Some(Focus::NewAlarm) | None => return WindowTouchAction::CreateAlarmView,
+                Some(Focus::Alarm(index)) => {
+                    return WindowTouchAction::EditAlarmView(self.alarms[index].clone());
+                },
+            },
+            // Remove the focused alarm.
+            KeyAction::Delete => {
+                if let Some(Focus::Alarm(index)) = self.focus {
+                    self.remove_alarm(self.alarms[index].id.clone());
+                }
+            },
+            // Enable or disable the focused alarm.
+            KeyAction::Toggle => {
+                if let Some(Focus::Alarm(index)) = self.focus {
+                    let alarm = &self.alarms[index];
+                    self.set_alarm_enabled(alarm.id.clone(), !alarm.enabled);
+                }
+            },
+            KeyAction::Back => self.dirty |= self.focus.take().is_some(),
+            KeyAction::Stop | KeyAction::Snooze => (),
+        }
+
+        WindowTouchAction::None
+    }
+
+    /// Remove an alarm.
+    fn remove_alarm(&self, id: String) {
+        let client = self.client.clone();
+        tokio::spawn(async move {
+            match client.remove(id).await {
+                // Ignore alarms which were removed by another client.
+                Err(AlarmError::AlarmNotFound(id)) => debug!("Alarm {id:?} was removed already"),
+                Err(err) => error!("Failed to remove alarm: {err}"),
+                Ok(()) => (),
+            }
+        });
+    }
+
+    /// Enable or disable an alarm.
+    fn set_alarm_enabled(&self, id: String, enabled: bool) {
+        let client = self.client.clone();
+        tokio::spawn(async move {
+            if let Err(err) = client.set_enabled(id, enabled).await {
+                error!("Failed to toggle alarm: {err}");
+            }
+        });
+    }
+
+    /// Move keyboard focus by `delta` items, with the new alarm button being
+    /// the last item.
+    fn move_focus(&mut self, delta: isize) {
+        let len = self.alarms.len();
+        let position = match self.focus {
+            Some(Focus::Alarm(index)) => (index as isize + delta).clamp(0, len as isize) as usize,
+            Some(Focus::NewAlarm) => (len as isize + delta).clamp(0, len as isize) as usize,
+            // Start at the bottommost alarm when moving up without focus.
+            None if delta < 0 => len.saturating_sub(1),
+            None => len,
+        };
+
+        self.focus = match position < len {
+            true => Some(Focus::Alarm(position)),
+            false => Some(Focus::NewAlarm),
+        };
+        self.dirty = true;
+
+        self.scroll_to_focus();
+    }
+
+    /// Scroll the focused alarm into view.
+    fn scroll_to_focus(&mut self) {
+        let index = match self.focus {
+            Some(Focus::Alarm(index)) => index,
+            _ => return,
+        };
+
+        let last_alarm_rect = Self::last_alarm_rect(self.size, self.scale);
+        let alarm_height = (last_alarm_rect.bottom - last_alarm_rect.top) as f64;
+        let alarms_end = last_alarm_rect.bottom as f64;
+
+        // Get the alarm's current vertical position.
+        let rindex = self.alarms.len() - 1 - index;
+        let top = last_alarm_rect.top as f64 - rindex as f64 * alarm_height + self.scroll_offset;
+        let bottom = top + alarm_height;
+
+        if top < 0. {
+            self.scroll_offset -= top;
+        } else if bottom > alarms_end {
+            self.scroll_offset -= bottom - alarms_end;
+        }
+        self.clamp_scroll_offset();
+    }
+
+    /// Number of fully visible alarms.
+    fn page_size(&self) -> usize {
+        let last_alarm_rect = Self::last_alarm_rect(self.size, self.scale);
+        let alarm_height = last_alarm_rect.bottom - last_alarm_rect.top;
+        ((last_alarm_rect.bottom / alarm_height).floor() as usize).max(1)
+    }
+
     /// Physical rectangle of the new alarm button.
     fn new_button_rect(size: Size<f32>, scale: f64) -> Rect {
         let padding = (OUTSIDE_PADDING * scale) as f32;
@@ -256,6 +524,17 @@ impl ListAlarms {
         Rect::new(x, y, x + button_width, y + button_height)
     }
 
+    /// Physical rectangle of the daemon unavailability banner.
+    fn banner_rect(size: Size<f32>, scale: f64) -> Rect {
+        let mut rect = Self::new_button_rect(size, scale);
+
+        let padding = (OUTSIDE_PADDING * scale) as f32;
+        rect.bottom -= rect.top - padding;
+        rect.top = padding;
+
+        rect
+    }
+
     /// Physical rectangle of the bottommost alarm.
     fn last_alarm_rect(size: Size<f32>, scale: f64) -> Rect {
         let new_rect = Self::new_button_rect(size, scale);
@@ -286,8 +565,23 @@ impl ListAlarms {
         Rect::new(x, y, x + size, y + size)
     }
 
+    /// Physical rectangle of the alarm enable/disable switch relative to the
+    /// alarm origin.
+    fn toggle_alarm_rect(size: Size<f32>, scale: f64) -> Rect {
+        let delete_rect = Self::delete_alarm_rect(size, scale);
+
+        let width = (TOGGLE_WIDTH * scale) as f32;
+        let height = (TOGGLE_HEIGHT * scale) as f32;
+        let padding = (BUTTON_PADDING * scale) as f32 / 2.;
+
+        let x = delete_rect.left - padding - width;
+        let y = delete_rect.top + (delete_rect.bottom - delete_rect.top - height) / 2.;
+
+        Rect::new(x, y, x + width, y + height)
+    }
+
     /// Get alarm at the specified location.
-    fn alarm_at(&self, mut point: Point<f32>) -> Option<(&Alarm, bool)> {
+    fn alarm_at(&self, mut point: Point<f32>) -> Option<(&Alarm, AlarmPart)> {
         let last_alarm_rect = Self::last_alarm_rect(self.size, self.scale);
 
         // Short-circuit if point is outside the alarm list.
@@ -307,13 +601,21 @@ impl ListAlarms {
         let rindex = (bottom_relative / alarm_height).floor() as usize;
         let index = self.alarms.len().saturating_sub(rindex + 1);
 
-        // Check if touch is within close button bounds.
+        // Check if touch is within close or switch button bounds.
         let delete_alarm_rect = Self::delete_alarm_rect(self.size, self.scale);
+        let toggle_alarm_rect = Self::toggle_alarm_rect(self.size, self.scale);
         let relative_x = (point.x - last_alarm_rect.left) as f64;
         let relative_y = (alarm_height - 1. - (bottom_relative % alarm_height)) as f64;
-        let delete = rect_contains(delete_alarm_rect, Point::new(relative_x, relative_y));
+        let relative_point = Point::new(relative_x, relative_y);
+        let part = if rect_contains(delete_alarm_rect, relative_point) {
+            AlarmPart::Delete
+        } else if rect_contains(toggle_alarm_rect, relative_point) {
+            AlarmPart::Toggle
+        } else {
+            AlarmPart::Body
+        };
 
-        Some((&self.alarms[index], delete))
+        Some((&self.alarms[index], part))
     }
 
     /// Clamp alarm list viewport offset.
@@ -350,12 +652,27 @@ struct TouchState {
     point: Point<f64>,
 }
 
+/// Keyboard focus target.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum Focus {
+    NewAlarm,
+    Alarm(usize),
+}
+
 /// Intention of a touch sequence.
 #[derive(Default)]
 enum TouchAction {
     #[default]
     None,
     CreateAlarm,
-    AlarmTap(String, bool),
+    AlarmTap(String, AlarmPart),
     AlarmDrag,
 }
+
+/// Touchable areas of an alarm list entry.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum AlarmPart {
+    Body,
+    Toggle,
+    Delete,
+}
@@ -10,7 +10,7 @@ use std::time::Instant;
 use skia_safe::textlayout::{FontCollection, TextStyle};
 use skia_safe::{Canvas, Color4f, FontMgr, Paint, Path, Rect};
 
-use crate::config::{Config, Input};
+use crate::config::{self, Config, Input};
 use crate::geometry::Point;
 
 /// Outer UI padding at scale 1.
@@ -41,7 +41,10 @@ pub struct RenderConfig {
     pub heading_text_style: TextStyle,
     pub text_style: TextStyle,
     pub input_config: Input,
+    pub alarm_config: config::Alarm,
     pub button_paint: Paint,
+    pub focus_paint: Paint,
+    pub highlight_paint: Paint,
     pub icon_paint: Paint,
     pub text_paint: Paint,
 }
@@ -61,6 +64,16 @@ impl RenderConfig {
         text_paint.set_color4f(config.colors.foreground.as_color4f(), None);
         text_paint.set_anti_alias(true);
 
+        let mut focus_paint = Paint::default();
+        focus_paint.set_color4f(config.colors.highlight.as_color4f(), None);
+        focus_paint.set_stroke_width(STROKE_WIDTH);
+        focus_paint.set_anti_alias(true);
+        focus_paint.set_stroke(true);
+
+        let mut highlight_paint = Paint::default();
+        highlight_paint.set_color4f(config.colors.highlight.as_color4f(), None);
+        highlight_paint.set_anti_alias(true);
+
         let font_family = config.font.family.clone();
         let font_size = config.font.size;
 
@@ -78,14 +91,17 @@ impl RenderConfig {
         Self {
             fonts: font_collection,
             heading_text_style,
+            highlight_paint,
             button_paint,
+            focus_paint,
             font_family,
             text_style,
             icon_paint,
             text_paint,
             font_size,
             background: config.colors.background.as_color4f(),
-            input_config: config.input,
+            input_config: config.input.clone(),
+            alarm_config: config.alarm.clone(),
         }
     }
 
@@ -96,6 +112,7 @@ impl RenderConfig {
         let mut dirty = false;
 
         let alt_background = config.colors.alt_background.as_color4f();
+        let highlight = config.colors.highlight.as_color4f();
         let foreground = config.colors.foreground.as_color4f();
         let background = config.colors.background.as_color4f();
 
@@ -115,6 +132,11 @@ impl RenderConfig {
             self.button_paint.set_color4f(alt_background, None);
             dirty = true;
         }
+        if self.focus_paint.color4f() != highlight {
+            self.focus_paint.set_color4f(highlight, None);
+            self.highlight_paint.set_color4f(highlight, None);
+            dirty = true;
+        }
         if self.text_paint.color4f() != foreground {
             self.text_paint.set_color4f(foreground, None);
             self.icon_paint.set_color4f(foreground, None);
@@ -127,13 +149,23 @@ impl RenderConfig {
             dirty = true;
         }
         if self.input_config != config.input {
-            self.input_config = config.input;
+            self.input_config = config.input.clone();
+        }
+        if self.alarm_config != config.alarm {
+            self.alarm_config = config.alarm.clone();
+            dirty = true;
         }
 
         dirty
     }
 }
 
+/// Draw the keyboard focus indicator around a rectangle.
+fn draw_focus(canvas: &Canvas, render_config: &RenderConfig, rect: Rect) {
+    let inset = render_config.focus_paint.stroke_width() / 2.;
+    canvas.draw_rect(rect.with_inset((inset, inset)), &render_config.focus_paint);
+}
+
 /// Button icons.
 #[derive(Debug)]
 enum Icon {
@@ -193,6 +225,15 @@ impl Icon {
     }
 }
 
+/// Pointer axis scroll distance.
+#[derive(Copy, Clone, Debug)]
+pub enum ScrollDelta {
+    /// Discrete scroll wheel steps.
+    Steps(i32),
+    /// Continuous scroll distance in logical pixels.
+    Pixels(f64),
+}
+
 /// Scroll velocity state.
 #[derive(Default)]
 pub struct ScrollVelocity {
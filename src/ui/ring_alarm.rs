@@ -3,26 +3,37 @@
 use rezz::Alarm;
 use skia_safe::textlayout::{ParagraphBuilder, ParagraphStyle, TextAlign};
 use skia_safe::{Canvas, Rect};
+use smithay_client_toolkit::reexports::protocols::wp::cursor_shape::v1::client::wp_cursor_shape_device_v1::Shape as CursorShape;
 use time::macros::format_description;
 use time::{Duration, OffsetDateTime, UtcOffset};
 
+use crate::config::KeyAction;
 use crate::geometry::{Point, Size, rect_contains};
 use crate::ui::window::TouchAction as WindowTouchAction;
-use crate::ui::{BUTTON_HEIGHT, OUTSIDE_PADDING, RenderConfig};
+use crate::ui::{BUTTON_HEIGHT, BUTTON_PADDING, OUTSIDE_PADDING, RenderConfig, draw_focus};
 
 /// Active ringing alarm UI state.
 pub struct RingAlarm {
     touch_state: TouchState,
+    focus: Option<Focus>,
 
     size: Size<f32>,
     scale: f64,
 
+    can_snooze: bool,
     dirty: bool,
 }
 
 impl Default for RingAlarm {
     fn default() -> Self {
-        Self { dirty: true, scale: 1., touch_state: Default::default(), size: Default::default() }
+        Self {
+            dirty: true,
+            scale: 1.,
+            touch_state: Default::default(),
+            can_snooze: Default::default(),
+            focus: Default::default(),
+            size: Default::default(),
+        }
     }
 }
 
@@ -41,6 +52,10 @@ impl RingAlarm {
         self.size = size.into();
         self.scale = scale;
 
+        // Only allow snoozing until the snooze limit is reached.
+        let alarm_config = &render_config.alarm_config;
+        self.can_snooze = alarm.snoozes < alarm_config.max_snoozes as u32;
+
         // Clear background.
         canvas.clear(render_config.background);
 
@@ -48,7 +63,7 @@ impl RingAlarm {
 
         // Convert Alarm's unix timestamp to a local time.
         let utc_offset = UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC);
-        let time = OffsetDateTime::UNIX_EPOCH + Duration::seconds(alarm.unix_time);
+        let time = OffsetDateTime::UNIX_EPOCH + Duration::seconds(alarm.ring_time());
         let local_time = time.to_offset(utc_offset);
         let time_format = format_description!("[hour]:[minute]");
         let time_str = local_time.format(&time_format).unwrap();
@@ -65,10 +80,29 @@ impl RingAlarm {
         let mut time_paragraph = time_builder.build();
         time_paragraph.layout(time_rect.right - time_rect.left);
 
-        // Draw label in the center of the button.
-        let y_offset = (time_rect.bottom - time_rect.top - time_paragraph.height()) / 2.;
+        // Create the alarm's label paragraph.
+        let label_paragraph = (!alarm.label.is_empty()).then(|| {
+            let mut label_style = ParagraphStyle::new();
+            label_style.set_text_style(&render_config.text_style);
+            label_style.set_text_align(TextAlign::Center);
+
+            let mut label_builder = ParagraphBuilder::new(&label_style, &render_config.fonts);
+            label_builder.add_text(&alarm.label);
+            let mut label_paragraph = label_builder.build();
+            label_paragraph.layout(time_rect.right - time_rect.left);
+            label_paragraph
+        });
+        let label_height = label_paragraph.as_ref().map_or(0., |paragraph| paragraph.height());
+
+        // Draw time and label in the center of the screen.
+        let time_height = time_paragraph.height();
+        let y_offset = (time_rect.bottom - time_rect.top - time_height - label_height) / 2.;
         let point = Point::new(time_rect.left, time_rect.top + y_offset);
         time_paragraph.paint(canvas, point);
+        if let Some(label_paragraph) = label_paragraph {
+            let point = Point::new(time_rect.left, point.y + time_height);
+            label_paragraph.paint(canvas, point);
+        }
 
         // Draw stop button.
 
@@ -91,6 +125,41 @@ impl RingAlarm {
         let y_offset = (stop_rect.bottom - stop_rect.top - stop_paragraph.height()) / 2.;
         let point = Point::new(stop_rect.left, stop_rect.top + y_offset);
         stop_paragraph.paint(canvas, point);
+
+        if self.focus == Some(Focus::Stop) {
+            draw_focus(canvas, render_config, stop_rect);
+        }
+
+        // Draw snooze button.
+
+        if !self.can_snooze {
+            return;
+        }
+
+        // Draw button background.
+        let snooze_rect = Self::snooze_button_rect(self.size, self.scale);
+        canvas.draw_rect(snooze_rect, &render_config.button_paint);
+
+        // Configure text rendering style.
+        let mut snooze_style = ParagraphStyle::new();
+        snooze_style.set_text_style(&render_config.text_style);
+        snooze_style.set_text_align(TextAlign::Center);
+
+        // Perform text shaping and layout.
+        let snooze_text = format!("Snooze {} Minutes", alarm_config.snooze_minutes);
+        let mut snooze_builder = ParagraphBuilder::new(&snooze_style, &render_config.fonts);
+        snooze_builder.add_text(snooze_text);
+        let mut snooze_paragraph = snooze_builder.build();
+        snooze_paragraph.layout(snooze_rect.right - snooze_rect.left);
+
+        // Draw label in the center of the button.
+        let y_offset = (snooze_rect.bottom - snooze_rect.top - snooze_paragraph.height()) / 2.;
+        let point = Point::new(snooze_rect.left, snooze_rect.top + y_offset);
+        snooze_paragraph.paint(canvas, point);
+
+        if self.focus == Some(Focus::Snooze) {
+            draw_focus(canvas, render_config, snooze_rect);
+        }
     }
 
     /// Check whether the UI requires a redraw.
@@ -98,17 +167,29 @@ impl RingAlarm {
         self.dirty
     }
 
+    /// Reset the UI state for a new alarm.
+    pub fn reset(&mut self) {
+        self.focus = None;
+        self.dirty = true;
+    }
+
     /// Handle touch press.
     pub fn touch_down(&mut self, logical_point: Point<f64>) {
+        // Hide keyboard focus during touch input.
+        self.dirty |= self.focus.take().is_some();
+
         // Convert position to physical space.
         let point = logical_point * self.scale;
         self.touch_state.point = point;
 
         // Get button geometries.
         let stop_rect = Self::stop_button_rect(self.size, self.scale);
+        let snooze_rect = Self::snooze_button_rect(self.size, self.scale);
 
         if rect_contains(stop_rect, point) {
             self.touch_state.action = TouchAction::Stop;
+        } else if self.can_snooze && rect_contains(snooze_rect, point) {
+            self.touch_state.action = TouchAction::Snooze;
         } else {
             self.touch_state.action = TouchAction::None;
         }
@@ -123,12 +204,72 @@ impl RingAlarm {
 
     /// Handle touch release.
     pub fn touch_up(&mut self) -> WindowTouchAction {
-        // Return to lists view, thereby automatically cancelling the alarm playback.
-        if let TouchAction::Stop = self.touch_state.action {
-            let rect = Self::stop_button_rect(self.size, self.scale);
-            if rect_contains(rect, self.touch_state.point) {
-                return WindowTouchAction::ListAlarmsView;
-            }
+        match self.touch_state.action {
+            // Return to lists view, thereby automatically cancelling the alarm playback.
+            TouchAction::Stop => {
+                let rect = Self::stop_button_rect(self.size, self.scale);
+                if rect_contains(rect, self.touch_state.point) {
+                    return WindowTouchAction::ListAlarmsView;
+                }
+            },
+            TouchAction::Snooze => {
+                let rect = Self::snooze_button_rect(self.size, self.scale);
+                if rect_contains(rect, self.touch_state.point) {
+                    return WindowTouchAction::SnoozeAlarm;
+                }
+            },
+            TouchAction::None => (),
+        }
+
+        WindowTouchAction::None
+    }
+
+    /// Handle touch cancellation.
+    pub fn touch_cancel(&mut self) {
+        self.touch_state = Default::default();
+    }
+
+    /// Get the cursor shape at a location.
+    pub fn cursor_shape(&self, logical_point: Point<f64>) -> CursorShape {
+        let point = logical_point * self.scale;
+
+        let stop_rect = Self::stop_button_rect(self.size, self.scale);
+        let snooze_rect = Self::snooze_button_rect(self.size, self.scale);
+
+        if rect_contains(stop_rect, point) || (self.can_snooze && rect_contains(snooze_rect, point))
+        {
+            CursorShape::Pointer
+        } else {
+            CursorShape::Default
+        }
+    }
+
+    /// Handle keyboard input.
+    pub fn key_press(&mut self, action: KeyAction) -> WindowTouchAction {
+        match action {
+            // Return to lists view, thereby automatically cancelling the alarm playback.
+            KeyAction::Stop => return WindowTouchAction::ListAlarmsView,
+            KeyAction::Snooze if self.can_snooze => return WindowTouchAction::SnoozeAlarm,
+            // Activate the focused button.
+            KeyAction::Confirm => match self.focus {
+                Some(Focus::Stop) => return WindowTouchAction::ListAlarmsView,
+                Some(Focus::Snooze) => return WindowTouchAction::SnoozeAlarm,
+                None => (),
+            },
+            // Move focus between the stop and snooze buttons.
+            KeyAction::Up
+            | KeyAction::Down
+            | KeyAction::PageUp
+            | KeyAction::PageDown
+            | KeyAction::Next
+            | KeyAction::Previous => {
+                self.focus = match self.focus {
+                    Some(Focus::Stop) if self.can_snooze => Some(Focus::Snooze),
+                    _ => Some(Focus::Stop),
+                };
+                self.dirty = true;
+            },
+            KeyAction::Snooze | KeyAction::Back | KeyAction::Delete | KeyAction::Toggle => (),
         }
 
         WindowTouchAction::None
@@ -151,6 +292,17 @@ impl RingAlarm {
 
         Rect::new(x, y, x + button_width, y + button_height)
     }
+
+    /// Physical rectangle of the snooze button.
+    fn snooze_button_rect(size: Size<f32>, scale: f64) -> Rect {
+        let mut rect = Self::stop_button_rect(size, scale);
+
+        let offset = rect.bottom - rect.top + (BUTTON_PADDING * scale) as f32;
+        rect.top -= offset;
+        rect.bottom -= offset;
+
+        rect
+    }
 }
 
 /// Touch event tracking.
@@ -160,10 +312,18 @@ struct TouchState {
     point: Point<f64>,
 }
 
+/// Keyboard focus target.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum Focus {
+    Stop,
+    Snooze,
+}
+
 /// Intention of a touch sequence.
 #[derive(Default)]
 enum TouchAction {
     #[default]
     None,
     Stop,
+    Snooze,
 }
@@ -1,19 +1,29 @@
 //! Wayland window rendering.
 
-use std::mem;
 use std::ptr::NonNull;
+use std::{future, mem};
 
-use alarm::Alarms;
-use alarm::audio::AlarmSound;
+use alarm::audio::{self, AlarmSound, SOUND_METADATA_KEY};
+use alarm::client::Client;
+use alarm::error::Error as AlarmError;
+use alarm::vibration::{Vibration, VibrationPattern};
 use glutin::display::{Display, DisplayApiPreference};
 use raw_window_handle::{RawDisplayHandle, WaylandDisplayHandle};
 use rezz::Alarm;
 use smithay_client_toolkit::compositor::{CompositorState, Region};
+use smithay_client_toolkit::reexports::client::protocol::wl_surface::WlSurface;
 use smithay_client_toolkit::reexports::client::{Connection, QueueHandle};
+use smithay_client_toolkit::reexports::protocols::wp::cursor_shape::v1::client::wp_cursor_shape_device_v1::Shape as CursorShape;
+use smithay_client_toolkit::reexports::protocols::wp::fractional_scale::v1::client::wp_fractional_scale_v1::WpFractionalScaleV1;
 use smithay_client_toolkit::reexports::protocols::wp::viewporter::client::wp_viewport::WpViewport;
+use smithay_client_toolkit::seat::keyboard::Keysym;
 use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shell::wlr_layer::{
+    Anchor, KeyboardInteractivity, Layer, LayerSurface,
+};
 use smithay_client_toolkit::shell::xdg::window::{Window as XdgWindow, WindowDecorations};
-use tracing::error;
+use tokio::task::JoinHandle;
+use tracing::{debug, error, warn};
 
 use crate::config::Config;
 use crate::geometry::{Point, Size};
@@ -22,28 +32,31 @@ use crate::ui::list_alarms::ListAlarms;
 use crate::ui::renderer::Renderer;
 use crate::ui::ring_alarm::RingAlarm;
 use crate::ui::skia::Canvas;
-use crate::ui::{RenderConfig, STROKE_WIDTH};
+use crate::ui::{RenderConfig, STROKE_WIDTH, ScrollDelta};
 use crate::wayland::ProtocolStates;
+use crate::wayland::idle_inhibit::{IdleInhibitManager, IdleInhibitor};
 use crate::{Error, State};
 
 /// Wayland window.
 pub struct Window {
     pub queue: QueueHandle<State>,
-    pub initial_draw_done: bool,
 
     connection: Connection,
-    xdg_window: XdgWindow,
-    viewport: WpViewport,
-    renderer: Renderer,
+    egl_display: Display,
+    surface: Option<WindowSurface>,
+    idle_inhibit: Option<IdleInhibitManager>,
 
     create_alarm: CreateAlarm,
     list_alarms: ListAlarms,
     ring_alarm: RingAlarm,
     view: View,
 
+    client: Client,
+
     render_config: RenderConfig,
-    canvas: Canvas,
 
+    hide_after_ring: bool,
+    axis_value120: i32,
     stalled: bool,
     dirty: bool,
     size: Size,
@@ -56,6 +69,8 @@ impl Window {
         connection: Connection,
         queue: QueueHandle<State>,
         config: &Config,
+        client: Client,
+        hidden: bool,
     ) -> Result<Self, Error> {
         // Get EGL display.
         let display = NonNull::new(connection.backend().display_ptr().cast()).unwrap();
@@ -63,47 +78,34 @@ impl Window {
         let raw_display = RawDisplayHandle::Wayland(wayland_display);
         let egl_display = unsafe { Display::new(raw_display, DisplayApiPreference::Egl)? };
 
-        // Create surface's Wayland global handles.
-        let surface = protocol_states.compositor.create_surface(&queue);
-        if let Some(fractional_scale) = &protocol_states.fractional_scale {
-            fractional_scale.fractional_scaling(&queue, &surface);
-        }
-        let viewport = protocol_states.viewporter.viewport(&queue, &surface);
-
-        // Create the XDG shell window.
-        let xdg_window = protocol_states.xdg_shell.create_window(
-            surface.clone(),
-            WindowDecorations::RequestClient,
-            &queue,
-        );
-        xdg_window.set_title("Aevum");
-        xdg_window.set_app_id("Aevum");
-        xdg_window.commit();
-
-        // Create OpenGL renderer.
-        let renderer = Renderer::new(egl_display, surface);
-
         // Default to a reasonable default size.
         let size = Size { width: 360, height: 720 };
 
-        Ok(Self {
+        let mut window = Self {
+            egl_display,
             connection,
-            xdg_window,
-            viewport,
-            renderer,
             queue,
             size,
+            idle_inhibit: protocol_states.idle_inhibit.clone(),
             render_config: RenderConfig::new(config),
+            create_alarm: CreateAlarm::new(client.clone()),
+            list_alarms: ListAlarms::new(client.clone()),
+            client,
             stalled: true,
             dirty: true,
             scale: 1.,
-            initial_draw_done: Default::default(),
-            create_alarm: Default::default(),
-            list_alarms: Default::default(),
+            hide_after_ring: Default::default(),
+            axis_value120: Default::default(),
             ring_alarm: Default::default(),
-            canvas: Default::default(),
+            surface: Default::default(),
             view: Default::default(),
-        })
+        };
+
+        if !hidden {
+            window.show(protocol_states, false);
+        }
+
+        Ok(window)
     }
 
     /// Redraw the window.
@@ -113,24 +115,32 @@ impl Window {
             self.stalled = true;
             return;
         }
-        self.initial_draw_done = true;
+
+        // Stall rendering until the surface is configured.
+        let surface = match &mut self.surface {
+            Some(surface) if surface.configured => surface,
+            _ => {
+                self.stalled = true;
+                return;
+            },
+        };
         self.dirty = false;
 
         // Update viewporter logical render size.
         //
         // NOTE: This must be done every time we draw with Sway; it is not
         // persisted when drawing with the same surface multiple times.
-        self.viewport.set_destination(self.size.width as i32, self.size.height as i32);
+        surface.viewport.set_destination(self.size.width as i32, self.size.height as i32);
 
         // Mark entire window as damaged.
-        let wl_surface = self.xdg_window.wl_surface();
+        let wl_surface = surface.shell.wl_surface();
         wl_surface.damage(0, 0, self.size.width as i32, self.size.height as i32);
 
         // Render the window content.
         let size = self.size * self.scale;
-        self.renderer.draw(size, |renderer| {
+        surface.renderer.draw(size, |renderer| {
             let config = &self.render_config;
-            self.canvas.draw(renderer.skia_config(), size, |canvas| match &self.view {
+            surface.canvas.draw(renderer.skia_config(), size, |canvas| match &self.view {
                 View::ListAlarms => self.list_alarms.draw(size, self.scale, canvas, config),
                 View::CreateAlarm => self.create_alarm.draw(size, self.scale, canvas, config),
                 View::RingAlarm(alarm, _) => {
@@ -146,6 +156,50 @@ impl Window {
         wl_surface.commit();
     }
 
+    /// Show the window.
+    ///
+    /// With `overlay`, the window is shown above all other windows and the
+    /// lock screen, if the compositor supports layer shell surfaces.
+    pub fn show(&mut self, protocol_states: &ProtocolStates, overlay: bool) {
+        let overlay = overlay && protocol_states.layer_shell.is_some();
+        if self.surface.as_ref().is_some_and(|surface| surface.is_overlay() == overlay) {
+            return;
+        }
+
+        // Destroy the old surface before creating its replacement.
+        self.surface = None;
+        let display = self.egl_display.clone();
+        self.surface = Some(WindowSurface::new(protocol_states, &self.queue, display, overlay));
+
+        // Wait for the initial configure before drawing.
+        self.stalled = true;
+        self.dirty = true;
+
+        let _ = self.connection.flush();
+    }
+
+    /// Hide the window.
+    pub fn hide(&mut self) {
+        self.surface = None;
+        let _ = self.connection.flush();
+    }
+
+    /// Handle surface configuration.
+    pub fn configure(&mut self, compositor: &CompositorState, size: Option<Size>) {
+        if let Some(size) = size {
+            self.set_size(compositor, size);
+        }
+
+        // Ensure we draw at least once after initial configure.
+        if let Some(surface) = &mut self.surface
+            && !mem::replace(&mut surface.configured, true)
+        {
+            self.update_opaque_region(compositor);
+            self.dirty = true;
+            self.draw();
+        }
+    }
+
     /// Unstall the renderer.
     ///
     /// This will render a new frame if there currently is no frame request
@@ -168,26 +222,72 @@ impl Window {
         self.unstall();
     }
 
+    /// Update the availability of the rezz daemon.
+    pub fn set_rezz_available(&mut self, available: bool) {
+        self.list_alarms.set_rezz_available(available);
+
+        self.unstall();
+    }
+
     /// Start alarm audio playback.
-    pub fn ring(&mut self, mut alarm: Alarm) {
-        // Immediately remove the alarm, to avoid other clients picking it up.
-        let id = mem::take(&mut alarm.id);
+    pub fn ring(&mut self, protocol_states: &ProtocolStates, alarm: Alarm) {
+        // Immediately dismiss the alarm, to avoid other clients picking it up.
+        let (client, id) = (self.client.clone(), alarm.id.clone());
         tokio::spawn(async move {
-            if let Err(err) = Alarms.remove(id).await {
-                error!("Failed to remove active alarm: {err}");
+            match client.dismiss(id).await {
+                // Another client picked up the alarm first.
+                Err(AlarmError::AlarmNotFound(id)) => debug!("Alarm {id:?} was dismissed already"),
+                Err(err) => error!("Failed to dismiss active alarm: {err}"),
+                Ok(()) => (),
             }
         });
 
+        let alarm_config = &self.render_config.alarm_config;
+
         // Start alarm sound playback.
-        let sound = match AlarmSound::play() {
-            Ok(sound) => sound,
-            Err(err) => {
-                error!("Failed to play alarm: {err}");
-                return;
+        let sound = if alarm_config.silent {
+            None
+        } else {
+            let sound = alarm.metadata.get(SOUND_METADATA_KEY).unwrap_or(&alarm_config.sound);
+            let sound = audio::sound_path(sound);
+            AlarmSound::play(sound.as_deref(), alarm_config.volume_ramp())
+                .inspect_err(|err| error!("Failed to play alarm: {err}"))
+                .ok()
+        };
+
+        // Start vibration.
+        let vibration = if alarm_config.vibration.is_empty() {
+            None
+        } else {
+            alarm_config
+                .vibration
+                .parse::<VibrationPattern>()
+                .and_then(|pattern| Vibration::start(pattern, None))
+                .inspect_err(|err| error!("Failed to vibrate: {err}"))
+                .ok()
+        };
+
+        // Remember whether the window needs to be hidden again after ringing.
+        if !matches!(self.view, View::RingAlarm(..)) {
+            self.hide_after_ring = self.surface.is_none();
+        }
+
+        // Show the alarm above other windows.
+        let overlay = alarm_config.overlay;
+        self.show(protocol_states, overlay);
+
+        // Keep the screen awake while ringing.
+        let idle_inhibitor = match (&self.surface, &self.idle_inhibit) {
+            (Some(surface), Some(idle_inhibit)) => {
+                Some(idle_inhibit.inhibit(&self.queue, surface.wl_surface()))
             },
+            _ => None,
         };
+        let logind_inhibitor = LogindInhibitor::new();
 
-        self.view = View::RingAlarm(alarm, sound);
+        let ringing = Ringing { sound, vibration, idle_inhibitor, logind_inhibitor };
+        self.view = View::RingAlarm(alarm, ringing);
+        self.ring_alarm.reset();
         self.dirty = true;
 
         self.unstall();
@@ -202,18 +302,27 @@ impl Window {
         self.size = size;
         self.dirty = true;
 
-        // Update the window's opaque region.
-        //
-        // This is done here since it can only change on resize, but the commit happens
-        // atomically on redraw.
-        if let Ok(region) = Region::new(compositor) {
-            region.add(0, 0, size.width as i32, size.height as i32);
-            self.xdg_window.wl_surface().set_opaque_region(Some(region.wl_region()));
-        }
+        self.update_opaque_region(compositor);
 
         self.unstall();
     }
 
+    /// Update the window's opaque region.
+    ///
+    /// This is done on resize since it can only change with the size, but the
+    /// commit happens atomically on redraw.
+    fn update_opaque_region(&self, compositor: &CompositorState) {
+        let surface = match &self.surface {
+            Some(surface) => surface,
+            None => return,
+        };
+
+        if let Ok(region) = Region::new(compositor) {
+            region.add(0, 0, self.size.width as i32, self.size.height as i32);
+            surface.wl_surface().set_opaque_region(Some(region.wl_region()));
+        }
+    }
+
     /// Update the window's DPI factor.
     pub fn set_scale_factor(&mut self, scale: f64) {
         if self.scale == scale {
@@ -226,6 +335,7 @@ impl Window {
         // Update scale-based render config cache data.
         self.render_config.text_style.set_font_size((self.render_config.font_size * scale) as f32);
         self.render_config.icon_paint.set_stroke_width(STROKE_WIDTH * scale as f32);
+        self.render_config.focus_paint.set_stroke_width(STROKE_WIDTH * scale as f32);
 
         self.unstall();
     }
@@ -258,19 +368,101 @@ impl Window {
         self.unstall();
     }
 
+    /// Handle touch cancellation.
+    ///
+    /// This resets the touch state of all views without executing any actions.
+    pub fn touch_cancel(&mut self) {
+        self.list_alarms.touch_cancel();
+        self.create_alarm.touch_cancel();
+        self.ring_alarm.touch_cancel();
+
+        self.unstall();
+    }
+
     /// Handle touch release.
-    pub fn touch_up(&mut self) {
+    pub fn touch_up(&mut self, protocol_states: &ProtocolStates) {
         let action = match &self.view {
             View::ListAlarms => self.list_alarms.touch_up(),
             View::CreateAlarm => self.create_alarm.touch_up(&self.render_config.input_config),
             View::RingAlarm(..) => self.ring_alarm.touch_up(),
         };
 
-        // Execute requested window actions.
+        self.handle_action(protocol_states, action);
+    }
+
+    /// Handle pointer axis scrolling.
+    ///
+    /// High-resolution scroll wheel events are accumulated into entire steps,
+    /// while `absolute` is used for continuous scrolling without wheel steps.
+    pub fn pointer_axis(&mut self, point: Point<f64>, value120: i32, absolute: f64) {
+        let delta = if value120 != 0 {
+            self.axis_value120 += value120;
+            let steps = self.axis_value120 / 120;
+            self.axis_value120 -= steps * 120;
+            ScrollDelta::Steps(steps)
+        } else {
+            ScrollDelta::Pixels(absolute)
+        };
+
+        match self.view {
+            View::ListAlarms => self.list_alarms.scroll(delta),
+            View::CreateAlarm => self.create_alarm.scroll(point, delta),
+            View::RingAlarm(..) => (),
+        }
+        self.unstall();
+    }
+
+    /// Get the cursor shape at a location.
+    pub fn cursor_shape(&self, point: Point<f64>) -> CursorShape {
+        match self.view {
+            View::ListAlarms => self.list_alarms.cursor_shape(point),
+            View::CreateAlarm => self.create_alarm.cursor_shape(point),
+            View::RingAlarm(..) => self.ring_alarm.cursor_shape(point),
+        }
+    }
+
+    /// Handle keyboard key press.
+    pub fn key_press(
+        &mut self,
+        protocol_states: &ProtocolStates,
+        keysym: Keysym,
+        text: Option<&str>,
+    ) {
+        let input_config = &self.render_config.input_config;
+        let key_action = match input_config.key_action(keysym, text) {
+            Some(key_action) => key_action,
+            None => return,
+        };
+
+        let action = match &self.view {
+            View::ListAlarms => self.list_alarms.key_press(key_action),
+            View::CreateAlarm => self.create_alarm.key_press(input_config, key_action),
+            View::RingAlarm(..) => self.ring_alarm.key_press(key_action),
+        };
+
+        self.handle_action(protocol_states, action);
+    }
+
+    /// Execute window actions requested by a view.
+    fn handle_action(&mut self, protocol_states: &ProtocolStates, action: TouchAction) {
+        let was_ringing = matches!(self.view, View::RingAlarm(..));
         match action {
             TouchAction::None => (),
             TouchAction::ListAlarmsView => {
-                self.view = View::ListAlarms;
+                // Stop ringing alarms, preventing them from being snoozed later.
+                if let View::RingAlarm(alarm, _) = mem::replace(&mut self.view, View::ListAlarms) {
+                    let client = self.client.clone();
+                    tokio::spawn(async move {
+                        match client.acknowledge(alarm.id).await {
+                            // The alarm was snoozed or removed in the meantime.
+                            Err(AlarmError::AlarmNotFound(id)) => {
+                                debug!("Alarm {id:?} is no longer ringing")
+                            },
+                            Err(err) => error!("Failed to acknowledge alarm: {err}"),
+                            Ok(()) => (),
+                        }
+                    });
+                }
                 self.dirty = true;
             },
             TouchAction::CreateAlarmView => {
@@ -278,6 +470,37 @@ impl Window {
                 self.create_alarm.reset();
                 self.dirty = true;
             },
+            TouchAction::EditAlarmView(alarm) => {
+                self.view = View::CreateAlarm;
+                self.create_alarm.edit(alarm);
+                self.dirty = true;
+            },
+            TouchAction::SnoozeAlarm => {
+                // Stop playback and delay the alarm.
+                if let View::RingAlarm(alarm, _) = mem::take(&mut self.view) {
+                    let minutes = self.render_config.alarm_config.snooze_minutes.into();
+                    let client = self.client.clone();
+                    tokio::spawn(async move {
+                        match client.snooze(alarm.id, minutes).await {
+                            Err(AlarmError::AlarmNotFound(id)) => {
+                                warn!("Cannot snooze alarm {id:?}: Alarm is no longer ringing")
+                            },
+                            Err(err) => error!("Failed to snooze alarm: {err}"),
+                            Ok(()) => (),
+                        }
+                    });
+                }
+                self.dirty = true;
+            },
+        }
+
+        // Return to the regular window state once the alarm stopped ringing.
+        if was_ringing && !matches!(self.view, View::RingAlarm(..)) {
+            if mem::take(&mut self.hide_after_ring) {
+                self.hide();
+            } else {
+                self.show(protocol_states, false);
+            }
         }
 
         self.unstall();
@@ -297,18 +520,157 @@ impl Window {
     }
 }
 
+/// Wayland surface with its rendering state.
+struct WindowSurface {
+    canvas: Canvas,
+    renderer: Renderer,
+    viewport: WpViewport,
+    fractional_scale: Option<WpFractionalScaleV1>,
+    shell: Shell,
+    configured: bool,
+}
+
+impl WindowSurface {
+    fn new(
+        protocol_states: &ProtocolStates,
+        queue: &QueueHandle<State>,
+        egl_display: Display,
+        overlay: bool,
+    ) -> Self {
+        // Create surface's Wayland global handles.
+        let surface = protocol_states.compositor.create_surface(queue);
+        let fractional_scale = protocol_states
+            .fractional_scale
+            .as_ref()
+            .map(|fractional_scale| fractional_scale.fractional_scaling(queue, &surface));
+        let viewport = protocol_states.viewporter.viewport(queue, &surface);
+
+        let shell = match protocol_states.layer_shell.as_ref().filter(|_| overlay) {
+            // Create a fullscreen overlay surface.
+            Some(layer_shell) => {
+                let layer = layer_shell.create_layer_surface(
+                    queue,
+                    surface.clone(),
+                    Layer::Overlay,
+                    Some("aevum"),
+                    None,
+                );
+                layer.set_anchor(Anchor::all());
+                layer.set_exclusive_zone(-1);
+                layer.set_keyboard_interactivity(KeyboardInteractivity::OnDemand);
+                layer.commit();
+                Shell::Layer(layer)
+            },
+            // Create the XDG shell window.
+            None => {
+                let xdg_window = protocol_states.xdg_shell.create_window(
+                    surface.clone(),
+                    WindowDecorations::RequestClient,
+                    queue,
+                );
+                xdg_window.set_title("Aevum");
+                xdg_window.set_app_id("Aevum");
+                xdg_window.commit();
+                Shell::Xdg(xdg_window)
+            },
+        };
+
+        // Create OpenGL renderer.
+        let renderer = Renderer::new(egl_display, surface);
+
+        Self {
+            fractional_scale,
+            renderer,
+            viewport,
+            shell,
+            configured: Default::default(),
+            canvas: Default::default(),
+        }
+    }
+
+    /// Get the underlying Wayland surface.
+    fn wl_surface(&self) -> &WlSurface {
+        self.shell.wl_surface()
+    }
+
+    /// Check whether this is a layer shell overlay surface.
+    fn is_overlay(&self) -> bool {
+        matches!(self.shell, Shell::Layer(_))
+    }
+}
+
+impl Drop for WindowSurface {
+    fn drop(&mut self) {
+        self.viewport.destroy();
+        if let Some(fractional_scale) = &self.fractional_scale {
+            fractional_scale.destroy();
+        }
+    }
+}
+
+/// Wayland shell surface role.
+enum Shell {
+    Xdg(XdgWindow),
+    Layer(LayerSurface),
+}
+
+impl Shell {
+    fn wl_surface(&self) -> &WlSurface {
+        match self {
+            Self::Xdg(window) => window.wl_surface(),
+            Self::Layer(layer) => layer.wl_surface(),
+        }
+    }
+}
+
 /// Available UI views.
 #[derive(Default)]
 enum View {
     #[default]
     ListAlarms,
     CreateAlarm,
-    RingAlarm(Alarm, #[allow(unused)] AlarmSound),
+    RingAlarm(Alarm, #[allow(unused)] Ringing),
+}
+
+/// Resources held while an alarm is ringing.
+///
+/// Everything is released once this is dropped, when the view changes.
+#[allow(unused)]
+struct Ringing {
+    sound: Option<AlarmSound>,
+    vibration: Option<Vibration>,
+    idle_inhibitor: Option<IdleInhibitor>,
+    logind_inhibitor: LogindInhibitor,
+}
+
+/// Logind idle inhibitor.
+///
+/// The inhibitor is released on drop.
+struct LogindInhibitor(JoinHandle<()>);
+
+impl LogindInhibitor {
+    fn new() -> Self {
+        Self(tokio::spawn(async {
+            match alarm::logind::inhibit_idle("Aevum", "Alarm is ringing").await {
+                // Hold the inhibitor until the task is aborted.
+                Ok(_inhibitor) => future::pending().await,
+                Err(err) => error!("Failed to inhibit logind idle: {err}"),
+            }
+        }))
+    }
+}
+
+impl Drop for LogindInhibitor {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
 }
 
-/// Window touch actions triggerable by downstream UIs.
+/// Window actions triggerable by downstream UIs.
 pub enum TouchAction {
     None,
     ListAlarmsView,
     CreateAlarmView,
+    EditAlarmView(Alarm),
+    SnoozeAlarm,
 }
@@ -0,0 +1,75 @@
+//! Handling of the idle inhibit protocol.
+
+use _ii::zwp_idle_inhibit_manager_v1::ZwpIdleInhibitManagerV1;
+use _ii::zwp_idle_inhibitor_v1::ZwpIdleInhibitorV1;
+use smithay_client_toolkit::globals::GlobalData;
+use smithay_client_toolkit::reexports::client::globals::{BindError, GlobalList};
+use smithay_client_toolkit::reexports::client::protocol::wl_surface::WlSurface;
+use smithay_client_toolkit::reexports::client::{
+    Connection, Dispatch, Proxy, QueueHandle, delegate_dispatch,
+};
+use smithay_client_toolkit::reexports::protocols::wp::idle_inhibit::zv1::client as _ii;
+
+use crate::State;
+
+/// Idle inhibit manager.
+#[derive(Clone, Debug)]
+pub struct IdleInhibitManager {
+    manager: ZwpIdleInhibitManagerV1,
+}
+
+impl IdleInhibitManager {
+    /// Create new idle inhibit manager.
+    pub fn new(globals: &GlobalList, queue_handle: &QueueHandle<State>) -> Result<Self, BindError> {
+        let manager = globals.bind(queue_handle, 1..=1, GlobalData)?;
+        Ok(Self { manager })
+    }
+
+    /// Inhibit idle while the surface is visible.
+    pub fn inhibit(&self, queue_handle: &QueueHandle<State>, surface: &WlSurface) -> IdleInhibitor {
+        let inhibitor = self.manager.create_inhibitor(surface, queue_handle, GlobalData);
+        IdleInhibitor { inhibitor }
+    }
+}
+
+/// Surface idle inhibitor.
+///
+/// The inhibitor is destroyed on drop.
+#[derive(Debug)]
+pub struct IdleInhibitor {
+    inhibitor: ZwpIdleInhibitorV1,
+}
+
+impl Drop for IdleInhibitor {
+    fn drop(&mut self) {
+        self.inhibitor.destroy();
+    }
+}
+
+impl Dispatch<ZwpIdleInhibitManagerV1, GlobalData, State> for IdleInhibitManager {
+    fn event(
+        _: &mut State,
+        _: &ZwpIdleInhibitManagerV1,
+        _: <ZwpIdleInhibitManagerV1 as Proxy>::Event,
+        _: &GlobalData,
+        _: &Connection,
+        _: &QueueHandle<State>,
+    ) {
+        // No events.
+    }
+}
+impl Dispatch<ZwpIdleInhibitorV1, GlobalData, State> for IdleInhibitManager {
+    fn event(
+        _: &mut State,
+        _: &ZwpIdleInhibitorV1,
+        _: <ZwpIdleInhibitorV1 as Proxy>::Event,
+        _: &GlobalData,
+        _: &Connection,
+        _: &QueueHandle<State>,
+    ) {
+        // No events.
+    }
+}
+
+delegate_dispatch!(State: [ZwpIdleInhibitManagerV1: GlobalData] => IdleInhibitManager);
+delegate_dispatch!(State: [ZwpIdleInhibitorV1: GlobalData] => IdleInhibitManager);
@@ -3,37 +3,54 @@
 use smithay_client_toolkit::compositor::{CompositorHandler, CompositorState};
 use smithay_client_toolkit::output::{OutputHandler, OutputState};
 use smithay_client_toolkit::reexports::client::globals::GlobalList;
+use smithay_client_toolkit::reexports::client::protocol::wl_keyboard::WlKeyboard;
 use smithay_client_toolkit::reexports::client::protocol::wl_output::{Transform, WlOutput};
 use smithay_client_toolkit::reexports::client::protocol::wl_pointer::WlPointer;
 use smithay_client_toolkit::reexports::client::protocol::wl_seat::WlSeat;
 use smithay_client_toolkit::reexports::client::protocol::wl_surface::WlSurface;
 use smithay_client_toolkit::reexports::client::protocol::wl_touch::WlTouch;
 use smithay_client_toolkit::reexports::client::{Connection, QueueHandle};
+use smithay_client_toolkit::reexports::protocols::wp::cursor_shape::v1::client::wp_cursor_shape_device_v1::{
+    Shape as CursorShape, WpCursorShapeDeviceV1,
+};
 use smithay_client_toolkit::registry::{ProvidesRegistryState, RegistryState};
+use smithay_client_toolkit::seat::keyboard::{
+    KeyEvent, KeyboardHandler, Keysym, Modifiers, RawModifiers,
+};
+use smithay_client_toolkit::seat::pointer::cursor_shape::CursorShapeManager;
 use smithay_client_toolkit::seat::pointer::{
     BTN_LEFT, PointerEvent, PointerEventKind, PointerHandler,
 };
 use smithay_client_toolkit::seat::touch::TouchHandler;
 use smithay_client_toolkit::seat::{Capability, SeatHandler, SeatState};
+use smithay_client_toolkit::shell::wlr_layer::{
+    LayerShell, LayerShellHandler, LayerSurface, LayerSurfaceConfigure,
+};
 use smithay_client_toolkit::shell::xdg::XdgShell;
 use smithay_client_toolkit::shell::xdg::window::{Window, WindowConfigure, WindowHandler};
 use smithay_client_toolkit::{
-    delegate_compositor, delegate_output, delegate_pointer, delegate_registry, delegate_seat,
-    delegate_touch, delegate_xdg_shell, delegate_xdg_window, registry_handlers,
+    delegate_compositor, delegate_keyboard, delegate_layer, delegate_output, delegate_pointer,
+    delegate_registry, delegate_seat, delegate_touch, delegate_xdg_shell, delegate_xdg_window,
+    registry_handlers,
 };
 
-use crate::geometry::Size;
+use crate::geometry::{Point, Size};
 use crate::wayland::fractional_scale::{FractionalScaleHandler, FractionalScaleManager};
+use crate::wayland::idle_inhibit::IdleInhibitManager;
 use crate::wayland::viewporter::Viewporter;
 use crate::{Error, State};
 
 pub mod fractional_scale;
+pub mod idle_inhibit;
 pub mod viewporter;
 
 /// Wayland protocol globals.
 #[derive(Debug)]
 pub struct ProtocolStates {
     pub fractional_scale: Option<FractionalScaleManager>,
+    pub cursor_shape: Option<CursorShapeManager>,
+    pub idle_inhibit: Option<IdleInhibitManager>,
+    pub layer_shell: Option<LayerShell>,
     pub compositor: CompositorState,
     pub registry: RegistryState,
     pub viewporter: Viewporter,
@@ -54,9 +71,23 @@ impl ProtocolStates {
         let viewporter = Viewporter::new(globals, queue)
             .map_err(|err| Error::WaylandProtocol("wp_viewporter", err))?;
         let fractional_scale = FractionalScaleManager::new(globals, queue).ok();
+        let idle_inhibit = IdleInhibitManager::new(globals, queue).ok();
+        let layer_shell = LayerShell::bind(globals, queue).ok();
+        let cursor_shape = CursorShapeManager::bind(globals, queue).ok();
         let seat = SeatState::new(globals, queue);
 
-        Ok(Self { fractional_scale, compositor, viewporter, xdg_shell, registry, output, seat })
+        Ok(Self {
+            fractional_scale,
+            cursor_shape,
+            idle_inhibit,
+            layer_shell,
+            compositor,
+            viewporter,
+            xdg_shell,
+            registry,
+            output,
+            seat,
+        })
     }
 }
 
@@ -132,7 +163,12 @@ impl WindowHandler for State {
         _queue: &QueueHandle<Self>,
         _window: &Window,
     ) {
-        self.terminated = true;
+        // Keep running in the background to show future alarms.
+        if self.background {
+            self.window.hide();
+        } else {
+            self.terminated = true;
+        }
     }
 
     fn configure(
@@ -143,20 +179,39 @@ impl WindowHandler for State {
         configure: WindowConfigure,
         _serial: u32,
     ) {
-        if let (Some(width), Some(height)) = configure.new_size {
-            let size = Size::new(width.get(), height.get());
-            self.window.set_size(&self.protocol_states.compositor, size);
-        }
-
-        // Ensure we draw at least once after initial configure.
-        if !self.window.initial_draw_done {
-            self.window.draw();
-        }
+        let size = match configure.new_size {
+            (Some(width), Some(height)) => Some(Size::new(width.get(), height.get())),
+            _ => None,
+        };
+        self.window.configure(&self.protocol_states.compositor, size);
     }
 }
 delegate_xdg_window!(State);
 delegate_xdg_shell!(State);
 
+impl LayerShellHandler for State {
+    fn closed(&mut self, _connection: &Connection, _queue: &QueueHandle<Self>, _: &LayerSurface) {
+        // Fall back to the XDG window if the overlay was closed by the compositor.
+        self.window.show(&self.protocol_states, false);
+    }
+
+    fn configure(
+        &mut self,
+        _connection: &Connection,
+        _queue: &QueueHandle<Self>,
+        _layer: &LayerSurface,
+        configure: LayerSurfaceConfigure,
+        _serial: u32,
+    ) {
+        let size = match configure.new_size {
+            (0, _) | (_, 0) => None,
+            (width, height) => Some(Size::new(width, height)),
+        };
+        self.window.configure(&self.protocol_states.compositor, size);
+    }
+}
+delegate_layer!(State);
+
 impl FractionalScaleHandler for State {
     fn scale_factor_changed(
         &mut self,
@@ -186,10 +241,19 @@ impl SeatHandler for State {
         match capability {
             Capability::Pointer if self.pointer.is_none() => {
                 self.pointer = self.protocol_states.seat.get_pointer(queue, &seat).ok();
+
+                // Create device for setting the cursor shape.
+                let cursor_shape = self.protocol_states.cursor_shape.as_ref();
+                self.pointer_state.cursor_shape_device = cursor_shape
+                    .zip(self.pointer.as_ref())
+                    .map(|(manager, pointer)| manager.get_shape_device(pointer, queue));
             },
             Capability::Touch if self.touch.is_none() => {
                 self.touch = self.protocol_states.seat.get_touch(queue, &seat).ok();
             },
+            Capability::Keyboard if self.keyboard.is_none() => {
+                self.keyboard = self.protocol_states.seat.get_keyboard(queue, &seat, None).ok();
+            },
             _ => (),
         }
     }
@@ -203,14 +267,26 @@ impl SeatHandler for State {
     ) {
         match capability {
             Capability::Pointer => {
+                if let Some(device) = self.pointer_state.cursor_shape_device.take() {
+                    device.destroy();
+                }
                 if let Some(pointer) = self.pointer.take() {
                     pointer.release();
                 }
+                self.pointer_state = Default::default();
             },
             Capability::Touch => {
                 if let Some(touch) = self.touch.take() {
                     touch.release();
                 }
+                if self.touch_id.take().is_some() {
+                    self.window.touch_cancel();
+                }
+            },
+            Capability::Keyboard => {
+                if let Some(keyboard) = self.keyboard.take() {
+                    keyboard.release();
+                }
             },
             _ => (),
         }
@@ -229,9 +305,15 @@ impl TouchHandler for State {
         _serial: u32,
         _time: u32,
         _surface: WlSurface,
-        _id: i32,
+        id: i32,
         position: (f64, f64),
     ) {
+        // Only follow the first active touch point.
+        if self.touch_id.is_some() {
+            return;
+        }
+        self.touch_id = Some(id);
+
         self.window.touch_down(position.into());
     }
 
@@ -241,10 +323,12 @@ impl TouchHandler for State {
         _queue: &QueueHandle<Self>,
         _touch: &WlTouch,
         _time: u32,
-        _id: i32,
+        id: i32,
         position: (f64, f64),
     ) {
-        self.window.touch_motion(&self.config, position.into());
+        if self.touch_id == Some(id) {
+            self.window.touch_motion(&self.config, position.into());
+        }
     }
 
     fn up(
@@ -254,12 +338,18 @@ impl TouchHandler for State {
         _touch: &WlTouch,
         _serial: u32,
         _time: u32,
-        _id: i32,
+        id: i32,
     ) {
-        self.window.touch_up();
+        if self.touch_id == Some(id) {
+            self.touch_id = None;
+            self.window.touch_up(&self.protocol_states);
+        }
     }
 
-    fn cancel(&mut self, _connection: &Connection, _queue: &QueueHandle<Self>, _touch: &WlTouch) {}
+    fn cancel(&mut self, _connection: &Connection, _queue: &QueueHandle<Self>, _touch: &WlTouch) {
+        self.touch_id = None;
+        self.window.touch_cancel();
+    }
 
     fn shape(
         &mut self,
@@ -284,6 +374,64 @@ impl TouchHandler for State {
 }
 delegate_touch!(State);
 
+impl KeyboardHandler for State {
+    fn enter(
+        &mut self,
+        _connection: &Connection,
+        _queue: &QueueHandle<Self>,
+        _keyboard: &WlKeyboard,
+        _surface: &WlSurface,
+        _serial: u32,
+        _raw: &[u32],
+        _keysyms: &[Keysym],
+    ) {
+    }
+
+    fn leave(
+        &mut self,
+        _connection: &Connection,
+        _queue: &QueueHandle<Self>,
+        _keyboard: &WlKeyboard,
+        _surface: &WlSurface,
+        _serial: u32,
+    ) {
+    }
+
+    fn press_key(
+        &mut self,
+        _connection: &Connection,
+        _queue: &QueueHandle<Self>,
+        _keyboard: &WlKeyboard,
+        _serial: u32,
+        event: KeyEvent,
+    ) {
+        self.window.key_press(&self.protocol_states, event.keysym, event.utf8.as_deref());
+    }
+
+    fn release_key(
+        &mut self,
+        _connection: &Connection,
+        _queue: &QueueHandle<Self>,
+        _keyboard: &WlKeyboard,
+        _serial: u32,
+        _event: KeyEvent,
+    ) {
+    }
+
+    fn update_modifiers(
+        &mut self,
+        _connection: &Connection,
+        _queue: &QueueHandle<Self>,
+        _keyboard: &WlKeyboard,
+        _serial: u32,
+        _modifiers: Modifiers,
+        _raw_modifiers: RawModifiers,
+        _layout: u32,
+    ) {
+    }
+}
+delegate_keyboard!(State);
+
 impl PointerHandler for State {
     fn pointer_frame(
         &mut self,
@@ -293,13 +441,31 @@ impl PointerHandler for State {
         events: &[PointerEvent],
     ) {
         for event in events {
+            let position = event.position.into();
+
             // Dispatch event to the window.
             match event.kind {
+                PointerEventKind::Enter { serial } => {
+                    self.pointer_state.enter_serial = serial;
+                    self.pointer_state.cursor_shape = None;
+                    self.update_cursor_shape(position);
+                },
                 PointerEventKind::Press { button: BTN_LEFT, .. } => {
-                    self.window.touch_down(event.position.into());
+                    self.pointer_state.pressed = true;
+                    self.window.touch_down(position);
                 },
                 PointerEventKind::Release { button: BTN_LEFT, .. } => {
-                    self.window.touch_up();
+                    self.pointer_state.pressed = false;
+                    self.window.touch_up(&self.protocol_states);
+                    self.update_cursor_shape(position);
+                },
+                // Handle dragging with the left button held down.
+                PointerEventKind::Motion { .. } if self.pointer_state.pressed => {
+                    self.window.touch_motion(&self.config, position);
+                },
+                PointerEventKind::Motion { .. } => self.update_cursor_shape(position),
+                PointerEventKind::Axis { vertical, .. } => {
+                    self.window.pointer_axis(position, vertical.value120, vertical.absolute);
                 },
                 _ => (),
             }
@@ -308,6 +474,31 @@ impl PointerHandler for State {
 }
 delegate_pointer!(State);
 
+impl State {
+    /// Update the cursor shape for the element below the pointer.
+    fn update_cursor_shape(&mut self, position: Point<f64>) {
+        let device = match &self.pointer_state.cursor_shape_device {
+            Some(device) => device,
+            None => return,
+        };
+
+        let shape = self.window.cursor_shape(position);
+        if self.pointer_state.cursor_shape != Some(shape) {
+            device.set_shape(self.pointer_state.enter_serial, shape);
+            self.pointer_state.cursor_shape = Some(shape);
+        }
+    }
+}
+
+/// Pointer input state.
+#[derive(Default)]
+pub struct PointerState {
+    cursor_shape_device: Option<WpCursorShapeDeviceV1>,
+    cursor_shape: Option<CursorShape>,
+    enter_serial: u32,
+    pressed: bool,
+}
+
 impl ProvidesRegistryState for State {
     registry_handlers![OutputState];
 